@@ -0,0 +1,33 @@
+use rug::Float;
+use rug::float::Constant;
+use strum_macros::{Display, EnumString, EnumVariantNames};
+
+/// Unit of the arguments of trigonometric functions.
+#[derive(Clone, Copy, PartialEq, Debug, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "kebab-case")]
+pub enum AngleUnit {
+    /// Radians, a full turn is 2π
+    Rad,
+    /// Degrees, a full turn is 360
+    Deg,
+}
+
+impl AngleUnit {
+    pub fn f64_to_radians(self, angle: f64) -> f64 {
+        return match self {
+            AngleUnit::Rad => angle,
+            AngleUnit::Deg => angle.to_radians(),
+        };
+    }
+
+    /// Converted with the precision of `angle`.
+    pub fn to_radians(self, angle: Float) -> Float {
+        return match self {
+            AngleUnit::Rad => angle,
+            AngleUnit::Deg => {
+                let pi = Float::with_val(angle.prec(), Constant::Pi);
+                angle * pi / 180
+            }
+        };
+    }
+}
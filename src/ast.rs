@@ -1,8 +1,13 @@
-use crate::operation_executor::{BinaryOperationExecutor, OperationExecutor, UnaryoperationExecutor};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::operation_executor::{BinaryOperationExecutor, FallibleBinaryOperationExecutor, LazyOperationExecutor, OperationExecutor, UnaryoperationExecutor};
 
 pub struct ExprCalculator<T: Clone> {
     pub(crate) operations: Vec<Operation<T>>,
     pub(crate) handler: Box<dyn PrimitiveHandler<T>>,
+    /// Infix operation inserted between adjacent operands, e.g. `*` for `2pi`
+    implicit_operation: Option<String>,
 }
 
 #[derive(Clone)]
@@ -16,21 +21,69 @@ pub enum OperationType {
 }
 pub struct Operation<T: Clone> {
     signature: String,
+    /// Other spellings of the signature, e.g. `×` for `*`
+    aliases: Vec<String>,
     description: String,
     op_type: OperationType,
-    operands: u8,
+    operands: usize,
     priority: u8,
-    calculate: Box<dyn OperationExecutor<T>>,
+    right_associative: bool,
+    /// Function accepting `operands` or more arguments
+    variadic: bool,
+    /// Second part of a ternary operation, e.g. `:` of `x ? y : z`
+    separator: Option<String>,
+    calculate: Executor<T>,
+}
+
+enum Executor<T: Clone> {
+    /// Receives already calculated operands
+    Eager(Box<dyn OperationExecutor<T>>),
+    /// Receives operands as thunks and calculates only the ones it needs
+    Lazy(Box<dyn LazyOperationExecutor<T>>),
+    /// Never calculated, merged into its ternary operation while building the AST
+    Separator,
+}
+
+impl<T: 'static + Clone> Clone for Executor<T> {
+    fn clone(&self) -> Self {
+        return match self {
+            Executor::Eager(calculate) => Executor::Eager(calculate.clone()),
+            Executor::Lazy(calculate) => Executor::Lazy(calculate.clone()),
+            Executor::Separator => Executor::Separator,
+        };
+    }
+}
+
+/// Not yet calculated operand of a lazy operation.
+pub struct Thunk<'a, T: Clone> {
+    node: &'a AstNode<T>,
+    variables: &'a Variables<T>,
+    failure: &'a RefCell<Option<Token<T>>>,
+}
+
+impl<'a, T: 'static + Clone> Thunk<'a, T> {
+    /// Calculates the operand. On error the failing token is kept, so it is reported instead of the lazy operation.
+    pub fn get(&self) -> Result<T, String> {
+        return self.node.calculate(self.variables).map_err(|token| {
+            let message = token.to_string();
+            self.failure.borrow_mut().get_or_insert(token);
+            message
+        });
+    }
 }
 
 impl<T: 'static + Clone> Clone for Operation<T> {
     fn clone(&self) -> Self {
         return Operation {
             signature: self.signature.clone(),
+            aliases: self.aliases.clone(),
             description: self.description.clone(),
             op_type: self.op_type.clone(),
             operands: self.operands,
             priority: self.priority,
+            right_associative: self.right_associative,
+            variadic: self.variadic,
+            separator: self.separator.clone(),
             calculate: self.calculate.clone(),
         };
     }
@@ -39,15 +92,27 @@ impl<T: 'static + Clone> Clone for Operation<T> {
 pub trait PrimitiveHandler<T> {
     fn from_string(&self, input: &String) -> Result<T, ()>;
     fn can_start_with(&self, input: String) -> bool;
+
+    /// Why `input`, which looked like a literal, can't be read. `None` when there is nothing more to tell.
+    fn explain(&self, _input: &String) -> Option<String> {
+        return Option::None;
+    }
+
+    /// Accept SI suffixes on number literals, handlers without numbers ignore it.
+    fn enable_si_suffixes(&mut self) {}
 }
 
 pub enum Token<T: Clone> {
     WhiteSpace { pos: usize, val: String },
     Open { pos: usize },
     Close { pos: usize },
+    Comma { pos: usize },
     Primitive { pos: usize, val: T, original: String },
+    Variable { pos: usize, val: String },
     Operation { pos: usize, val: Box<Operation<T>> },
     Unknown { pos: usize, val: String },
+    Malformed { pos: usize, val: String, message: String },
+    Failure { pos: usize, val: String, message: String },
 }
 
 impl<T: 'static + Clone> Clone for Token<T> {
@@ -56,25 +121,63 @@ impl<T: 'static + Clone> Clone for Token<T> {
             Token::WhiteSpace { pos, val } => Token::WhiteSpace { pos: *pos, val: val.clone() },
             Token::Open { pos } => Token::Open { pos: *pos },
             Token::Close { pos } => Token::Close { pos: *pos },
+            Token::Comma { pos } => Token::Comma { pos: *pos },
             Token::Primitive { pos, val, original } => Token::Primitive { pos: *pos, val: val.clone(), original: original.clone() },
+            Token::Variable { pos, val } => Token::Variable { pos: *pos, val: val.clone() },
             Token::Operation { pos, val } => Token::Operation { pos: *pos, val: val.clone() },
             Token::Unknown { pos, val } => Token::Unknown { pos: *pos, val: val.clone() },
+            Token::Malformed { pos, val, message } => Token::Malformed { pos: *pos, val: val.clone(), message: message.clone() },
+            Token::Failure { pos, val, message } => Token::Failure { pos: *pos, val: val.clone(), message: message.clone() },
         };
     }
 }
 
+pub type Variables<T> = HashMap<String, T>;
+
+/// Kind of a token of the input, for syntax highlighting.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TokenKind {
+    Literal,
+    Variable,
+    Constant,
+    Function,
+    /// Prefix, postfix and infix operations, also the ones spelled as words such as `and`
+    Operator,
+    Parenthesis,
+    Comma,
+    /// Token which makes the input invalid
+    Invalid,
+}
+
+/// Token of the input: its kind and the chars `start..end` it was read from.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Span {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Parsed expression which can be evaluated many times with different variable values.
+pub struct Expression<T: Clone> {
+    ast: AstNode<T>,
+}
+
 enum AstNode<T: Clone> {
     Primitive { val: T, token: Token<T> },
+    Variable { name: String, token: Token<T> },
     Unary { op: Box<Operation<T>>, p1: Box<AstNode<T>>, token: Token<T> },
     Binary { op: Box<Operation<T>>, p1: Box<AstNode<T>>, p2: Box<AstNode<T>>, token: Token<T> },
+    Nary { op: Box<Operation<T>>, args: Vec<AstNode<T>>, token: Token<T> },
 }
 
 impl<T: 'static + Clone> Clone for AstNode<T> {
     fn clone(&self) -> Self {
         return match self {
             AstNode::Primitive { val, token } => AstNode::Primitive { val: val.clone(), token: token.clone() },
+            AstNode::Variable { name, token } => AstNode::Variable { name: name.clone(), token: token.clone() },
             AstNode::Unary { op, p1, token } => AstNode::Unary { op: op.clone(), p1: p1.clone(), token: token.clone() },
             AstNode::Binary { op, p1, p2, token } => AstNode::Binary { op: op.clone(), p1: p1.clone(), p2: p2.clone(), token: token.clone() },
+            AstNode::Nary { op, args, token } => AstNode::Nary { op: op.clone(), args: args.clone(), token: token.clone() },
         };
     }
 }
@@ -84,6 +187,7 @@ enum State {
     Empty,
     Primitive,
     Operation,
+    Identifier,
     WhiteSpace,
 }
 
@@ -116,9 +220,25 @@ impl<T: 'static + Clone> ExprCalculator<T> {
         return ExprCalculator {
             operations: Vec::<Operation<T>>::new(),
             handler,
+            implicit_operation: None,
         };
     }
 
+    /// Opt-in implicit multiplication: `2pi`, `3(x+1)`, `2x`, `(a)(b)` and `2 sin(x)` are calculated as if
+    /// the infix operation `signature` stood between the operands, with the same priority, so `1/2pi` is `(1/2)*pi`.
+    /// A number never continues an implicit product (`(1)2` and `x 2` stay errors), and a number literal is read
+    /// as far as possible first, so `2e3` is 2000 while `2 e` is `2*e`.
+    /// Calculators without such infix operation ignore it.
+    pub fn enable_implicit_multiplication(&mut self, signature: &str) {
+        self.implicit_operation = Some(signature.to_string());
+    }
+
+    /// Reads `4.7k`, `10M` or `3u` as number literals. A suffix letter right after a number then always
+    /// belongs to the number, so `2m` is 0.002 rather than `2*m`.
+    pub fn enable_si_suffixes(&mut self) {
+        self.handler.enable_si_suffixes();
+    }
+
     pub fn add(
         &mut self,
         signature: String,
@@ -130,11 +250,15 @@ impl<T: 'static + Clone> ExprCalculator<T> {
     ) {
         self.operations.push(Operation {
             signature,
+            aliases: Vec::new(),
             description,
             op_type,
-            operands,
+            operands: usize::from(operands),
             priority: order,
-            calculate,
+            right_associative: false,
+            variadic: false,
+            separator: None,
+            calculate: Executor::Eager(calculate),
         });
     }
 
@@ -168,22 +292,30 @@ impl<T: 'static + Clone> ExprCalculator<T> {
     ) {
         self.operations.push(Operation {
             signature,
+            aliases: Vec::new(),
             description,
             op_type,
             operands: 1,
             priority: order,
-            calculate: Box::new(move |operands| { calculate(operands[0].clone()) }),
+            right_associative: false,
+            variadic: false,
+            separator: None,
+            calculate: Executor::Eager(Box::new(move |operands| { Result::Ok(calculate(operands[0].clone())) })),
         });
     }
 
     pub fn add_constant(&mut self, signature: String, description: String, value: T) {
         self.operations.push(Operation {
             signature,
+            aliases: Vec::new(),
             description,
             op_type: OperationType::Constant,
             operands: 0,
             priority: u8::MAX,
-            calculate: Box::new(move |_| { value.clone() }),
+            right_associative: false,
+            variadic: false,
+            separator: None,
+            calculate: Executor::Eager(Box::new(move |_| { Result::Ok(value.clone()) })),
         });
     }
 
@@ -196,38 +328,232 @@ impl<T: 'static + Clone> ExprCalculator<T> {
     ) {
         self.operations.push(Operation {
             signature,
+            aliases: Vec::new(),
             description,
             op_type: OperationType::Function,
             operands: 1,
             priority: order,
-            calculate: Box::new(move |operands| { calculate(operands[0].clone()) }),
+            right_associative: false,
+            variadic: false,
+            separator: None,
+            calculate: Executor::Eager(Box::new(move |operands| { Result::Ok(calculate(operands[0].clone())) })),
+        });
+    }
+
+    pub fn add_function(
+        &mut self,
+        signature: String,
+        description: String,
+        calculate: Box<dyn OperationExecutor<T>>,
+        operands: u8,
+        order: u8,
+    ) {
+        self.add(signature, description, OperationType::Function, calculate, operands, order);
+    }
+
+    pub fn add_lazy_function(
+        &mut self,
+        signature: String,
+        description: String,
+        calculate: Box<dyn LazyOperationExecutor<T>>,
+        operands: u8,
+        order: u8,
+    ) {
+        self.operations.push(Operation {
+            signature,
+            aliases: Vec::new(),
+            description,
+            op_type: OperationType::Function,
+            operands: usize::from(operands),
+            priority: order,
+            right_associative: false,
+            variadic: false,
+            separator: None,
+            calculate: Executor::Lazy(calculate),
+        });
+    }
+
+    /// Lazy function accepting `operands` or more arguments, e.g. `piecewise(c1, v1, c2, v2, default)`.
+    pub fn add_lazy_variadic_function(
+        &mut self,
+        signature: String,
+        description: String,
+        calculate: Box<dyn LazyOperationExecutor<T>>,
+        operands: u8,
+        order: u8,
+    ) {
+        self.operations.push(Operation {
+            signature,
+            aliases: Vec::new(),
+            description,
+            op_type: OperationType::Function,
+            operands: usize::from(operands),
+            priority: order,
+            right_associative: false,
+            variadic: true,
+            separator: None,
+            calculate: Executor::Lazy(calculate),
         });
     }
 
     pub fn add_infix(&mut self, signature: String, description: String, calculate: Box<dyn BinaryOperationExecutor<T>>, order: u8) {
+        self.add_fallible_infix(
+            signature,
+            description,
+            Box::new(move |op1, op2| { Result::Ok(calculate(op1, op2)) }),
+            order,
+        );
+    }
+
+    pub fn add_fallible_infix(
+        &mut self,
+        signature: String,
+        description: String,
+        calculate: Box<dyn FallibleBinaryOperationExecutor<T>>,
+        order: u8,
+    ) {
+        self.push_infix(signature, description, calculate, order, false);
+    }
+
+    /// Infix operation grouping from the right when chained with the same priority: `a^b^c` is `a^(b^c)`.
+    pub fn add_right_infix(&mut self, signature: String, description: String, calculate: Box<dyn BinaryOperationExecutor<T>>, order: u8) {
+        self.add_fallible_right_infix(
+            signature,
+            description,
+            Box::new(move |op1, op2| { Result::Ok(calculate(op1, op2)) }),
+            order,
+        );
+    }
+
+    pub fn add_fallible_right_infix(
+        &mut self,
+        signature: String,
+        description: String,
+        calculate: Box<dyn FallibleBinaryOperationExecutor<T>>,
+        order: u8,
+    ) {
+        self.push_infix(signature, description, calculate, order, true);
+    }
+
+    /// Infix operation which decides itself whether to calculate its operands, e.g. short-circuit AND.
+    pub fn add_lazy_infix(&mut self, signature: String, description: String, calculate: Box<dyn LazyOperationExecutor<T>>, order: u8) {
         self.operations.push(Operation {
             signature,
+            aliases: Vec::new(),
             description,
             op_type: OperationType::Infix,
             operands: 2,
             priority: order,
-            calculate: Box::new(move |operands| { calculate(operands[0].clone(), operands[1].clone()) }),
+            right_associative: false,
+            variadic: false,
+            separator: None,
+            calculate: Executor::Lazy(calculate),
+        });
+    }
+
+    /// Ternary operation `x ? y : z`, chained from the right: `a ? b : c ? d : e` is `a ? b : (c ? d : e)`.
+    /// Calculates its operands lazily like `add_lazy_infix`.
+    pub fn add_ternary(
+        &mut self,
+        signature: String,
+        separator: String,
+        description: String,
+        calculate: Box<dyn LazyOperationExecutor<T>>,
+        order: u8,
+    ) {
+        self.operations.push(Operation {
+            signature: separator.clone(),
+            aliases: Vec::new(),
+            description: String::new(),
+            op_type: OperationType::Infix,
+            operands: 2,
+            priority: order,
+            right_associative: true,
+            variadic: false,
+            separator: None,
+            calculate: Executor::Separator,
+        });
+        self.operations.push(Operation {
+            signature,
+            aliases: Vec::new(),
+            description,
+            op_type: OperationType::Infix,
+            operands: 2,
+            priority: order,
+            right_associative: true,
+            variadic: false,
+            separator: Some(separator),
+            calculate: Executor::Lazy(calculate),
+        });
+    }
+
+    fn push_infix(
+        &mut self,
+        signature: String,
+        description: String,
+        calculate: Box<dyn FallibleBinaryOperationExecutor<T>>,
+        order: u8,
+        right_associative: bool,
+    ) {
+        self.operations.push(Operation {
+            signature,
+            aliases: Vec::new(),
+            description,
+            op_type: OperationType::Infix,
+            operands: 2,
+            priority: order,
+            right_associative,
+            variadic: false,
+            separator: None,
+            calculate: Executor::Eager(Box::new(move |operands| { calculate(operands[0].clone(), operands[1].clone()) })),
         });
     }
 }
 
 impl<T: 'static + Clone> ExprCalculator<T> {
     pub fn calculate(&self, input: &str) -> Result<T, Token<T>> {
+        return self.calculate_with(input, &Variables::new());
+    }
+
+    pub fn calculate_with(&self, input: &str, variables: &Variables<T>) -> Result<T, Token<T>> {
+        return self.compile(input)?.calculate(variables);
+    }
+
+    pub fn compile(&self, input: &str) -> Result<Expression<T>, Token<T>> {
         let tokens = self.tokenize(input)?;
         let ast = self.build_ast(&tokens)?;
-        let result = ast.calculate();
 
-        return Result::Ok(result.ok().unwrap().clone());
+        return Result::Ok(Expression { ast });
+    }
+
+    /// Registers another spelling of all operations with `signature`, e.g. `√` for `sqrt`.
+    pub fn add_alias(&mut self, signature: &str, alias: &str) {
+        for operation in self.operations.iter_mut().filter(|it| it.signature == signature) {
+            operation.aliases.push(alias.to_string());
+        }
+    }
+
+    /// Whether `text` is a spelling of an operation, such names can't be variables.
+    pub fn is_operation(&self, text: &str) -> bool {
+        return self.operations.iter().any(|operation| operation.spellings().any(|it| it.as_str() == text));
+    }
+
+    /// Spellings of operations which are names, such as `sqrt`, `pi` or `and`, sorted.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.operations.iter()
+            .flat_map(|operation| operation.spellings())
+            .filter(|it| is_identifier(it))
+            .cloned()
+            .collect();
+        names.sort();
+        names.dedup();
+
+        return names;
     }
 
     fn can_be_operation(&self, text: &String) -> bool {
         for operation in self.operations.iter() {
-            if operation.signature.starts_with(text) {
+            if operation.spellings().any(|it| it.starts_with(text.as_str())) {
                 return true;
             }
         }
@@ -237,7 +563,7 @@ impl<T: 'static + Clone> ExprCalculator<T> {
 
     fn make_by_type(&self, value: &String, op_type: OperationType) -> Option<&Operation<T>> {
         for operation in self.operations.iter() {
-            if operation.signature == *value && operation.op_type == op_type {
+            if operation.op_type == op_type && operation.spellings().any(|it| it == value) {
                 return Option::Some(&operation);
             }
         }
@@ -245,17 +571,68 @@ impl<T: 'static + Clone> ExprCalculator<T> {
         return None;
     }
 
+    /// Tokens of `input` for syntax highlighting, whitespace left out. The token at which the input becomes
+    /// invalid is `TokenKind::Invalid`, nothing after a token which can't be read is given.
+    pub fn spans(&self, input: &str) -> Vec<Span> {
+        let mut context = Context::new();
+        let unreadable = self.scan(input, &mut context).err();
+        let tokens = context.get_tokens();
+        let invalid = match &unreadable {
+            Some(token) => Some(token.clone()),
+            None => self.build_ast(tokens).err(),
+        };
+
+        let mut spans = Vec::new();
+        for (index, token) in tokens.iter().enumerate() {
+            let end = match tokens.get(index + 1) {
+                Some(next) => next.get_pos(),
+                None => unreadable.as_ref().map_or(input.chars().count(), |it| it.get_pos()),
+            };
+            let kind = match token {
+                Token::WhiteSpace { .. } => continue,
+                _ if invalid.as_ref().map_or(false, |it| it.get_pos() == token.get_pos()) => TokenKind::Invalid,
+                Token::Open { .. } | Token::Close { .. } => TokenKind::Parenthesis,
+                Token::Comma { .. } => TokenKind::Comma,
+                Token::Primitive { .. } => TokenKind::Literal,
+                Token::Variable { .. } => TokenKind::Variable,
+                Token::Operation { pos: _, val } => match val.op_type {
+                    OperationType::Constant => TokenKind::Constant,
+                    OperationType::Function => TokenKind::Function,
+                    _ => TokenKind::Operator,
+                },
+                _ => TokenKind::Invalid,
+            };
+            spans.push(Span { kind, start: token.get_pos(), end });
+        }
+        if let Some(token) = unreadable {
+            let start = token.get_pos();
+            spans.push(Span { kind: TokenKind::Invalid, start, end: start + token.get_value().chars().count().max(1) });
+        }
+
+        return spans;
+    }
+
     fn tokenize(&self, input: &str) -> Result<Vec<Token<T>>, Token<T>> {
         let mut context = Context::new();
+        self.scan(input, &mut context)?;
 
-        for entry in input.chars().into_iter().enumerate() {
+        return Result::Ok(context.get_tokens().clone());
+    }
+
+    /// Reads tokens of `input` into `context` until one can't be read.
+    fn scan(&self, input: &str, context: &mut Context<T>) -> Result<(), Token<T>> {
+        let chars: Vec<char> = input.chars().collect();
+
+        for entry in chars.iter().enumerate() {
             let pos = entry.0;
-            let val = entry.1;
+            // a backslash at the end of a line continues the expression on the next one
+            let val = if *entry.1 == '\\' && is_line_end(&chars[pos + 1..]) { ' ' } else { *entry.1 };
 
             match context.state {
                 State::Empty => context.init_token_creation(pos, val, self)?,
                 _ if context.is_suitable_for_current_state(val, self) => context.add_symbol(val),
                 State::Primitive if context.can_add_to_operation(val, self) => context.mutate_to_operation(val),
+                _ if context.can_add_to_identifier(val) => context.mutate_to_identifier(val),
                 _ => {
                     context.collect_token(self)?;
                     context.init_token_creation(pos, val, self)?
@@ -267,7 +644,7 @@ impl<T: 'static + Clone> ExprCalculator<T> {
             context.collect_token(self)?;
         }
 
-        return Result::Ok(context.get_tokens().clone());
+        return Result::Ok(());
     }
 }
 
@@ -275,38 +652,51 @@ impl<T: 'static + Clone> ExprCalculator<T> {
     fn build_ast(&self, tokens: &Vec<Token<T>>) -> Result<AstNode<T>, Token<T>> {
         let mut stack: Vec<Token<T>> = Vec::new();
         let mut operands: Vec<AstNode<T>> = Vec::new();
+        let mut arguments: Vec<usize> = Vec::new();
+        let mut previous: Option<&Token<T>> = None;
 
         for token in tokens {
+            if matches!(token, Token::WhiteSpace {..}) {
+                continue;
+            }
+            if previous.map_or(false, |it| it.ends_operand()) && token.starts_operand() {
+                match self.make_implicit_operation(token) {
+                    Some(implicit) => self.push_operation(&mut stack, &mut operands, &implicit)?,
+                    None => return Result::Err(token.clone()),
+                }
+            }
+            previous = Some(token);
+
             match token {
                 Token::Primitive { pos: _pos, val, .. } => operands.push(AstNode::Primitive { val: val.clone(), token: token.clone() }),
-                Token::Open { .. } => stack.push(token.clone()),
+                Token::Variable { pos: _pos, val } => operands.push(AstNode::Variable { name: val.clone(), token: token.clone() }),
+                Token::Open { .. } => {
+                    stack.push(token.clone());
+                    arguments.push(1);
+                }
+                Token::Comma { .. } => {
+                    self.reduce_to_open(&mut stack, &mut operands, token)?;
+                    *arguments.last_mut().unwrap() += 1;
+                }
                 Token::Close { .. } => {
-                    loop {
-                        let last = stack.last();
-                        if last.is_none() {
-                            return Result::Err(token.clone());
-                        }
-                        if matches!(stack.last().unwrap(), Token::Open {..}) {
-                            break;
-                        }
-                        self.make_node(&mut operands, stack.pop().unwrap())?;
-                    };
+                    self.reduce_to_open(&mut stack, &mut operands, token)?;
                     stack.pop();
-                }
-                Token::Operation { pos: _, val } => {
-                    while !stack.is_empty() {
-                        let last_op = match stack.last().unwrap().clone() {
-                            Token::Operation { pos: _, val } => val,
-                            Token::Open { .. } => break,
-                            _ => return Result::Err(stack.last().unwrap().clone())
-                        };
-                        if last_op.priority <= val.priority {
-                            break;
+                    let count = arguments.pop().unwrap();
+                    match stack.last() {
+                        Some(Token::Operation { pos: _, val }) if val.op_type == OperationType::Function => {
+                            if val.variadic && val.operands <= count {
+                                if let Some(Token::Operation { pos: _, val }) = stack.last_mut() {
+                                    val.operands = count;
+                                }
+                            } else if val.operands != count {
+                                return Result::Err(stack.last().unwrap().clone());
+                            }
                         }
-                        self.make_node(&mut operands, stack.pop().unwrap())?;
+                        _ if count != 1 => return Result::Err(token.clone()),
+                        _ => (),
                     }
-                    stack.push(token.clone())
                 }
+                Token::Operation { .. } => self.push_operation(&mut stack, &mut operands, token)?,
                 _ => (),
             }
         }
@@ -315,7 +705,87 @@ impl<T: 'static + Clone> ExprCalculator<T> {
             self.make_node(&mut operands, stack.pop().unwrap())?;
         };
 
-        return Result::Ok(operands.pop().unwrap());
+        if operands.len() > 1 {
+            // every operand is consumed by an operation, but the last one which is the result
+            return Result::Err(operands[0].token().clone());
+        }
+
+        return match operands.pop() {
+            Some(it) => Result::Ok(it),
+            // nothing but whitespace, or empty parentheses
+            None => Result::Err(match tokens.iter().find(|it| !matches!(it, Token::WhiteSpace {..})) {
+                Some(token) => token.clone(),
+                None => Token::Malformed { pos: 0, val: String::new(), message: "missing expression".to_string() },
+            }),
+        };
+    }
+
+    fn push_operation(&self, stack: &mut Vec<Token<T>>, operands: &mut Vec<AstNode<T>>, token: &Token<T>) -> Result<(), Token<T>> {
+        let val = match token {
+            Token::Operation { pos: _, val } => val,
+            _ => return Result::Err(token.clone()),
+        };
+
+        let has_left_operand = val.op_type == OperationType::Infix || val.op_type == OperationType::Postfix;
+        while has_left_operand && !stack.is_empty() {
+            let last_op = match stack.last().unwrap().clone() {
+                Token::Operation { pos: _, val } => val,
+                Token::Open { .. } => break,
+                _ => return Result::Err(stack.last().unwrap().clone())
+            };
+            if last_op.priority < val.priority || (last_op.priority == val.priority && val.right_associative) {
+                break;
+            }
+            self.make_node(operands, stack.pop().unwrap())?;
+        }
+        if val.is_separator() {
+            self.reduce_to_ternary(stack, operands, token)?;
+        }
+        stack.push(token.clone());
+
+        return Result::Ok(());
+    }
+
+    /// Operation token inserted before `token` by implicit multiplication, if it applies.
+    fn make_implicit_operation(&self, token: &Token<T>) -> Option<Token<T>> {
+        if matches!(token, Token::Primitive { .. }) {
+            return None;
+        }
+
+        let op = self.make_by_type(self.implicit_operation.as_ref()?, OperationType::Infix)?;
+
+        return Some(Token::Operation { pos: token.get_pos(), val: Box::new(op.clone()) });
+    }
+
+    fn reduce_to_open(&self, stack: &mut Vec<Token<T>>, operands: &mut Vec<AstNode<T>>, token: &Token<T>) -> Result<(), Token<T>> {
+        loop {
+            let last = stack.last();
+            if last.is_none() {
+                return Result::Err(token.clone());
+            }
+            if matches!(stack.last().unwrap(), Token::Open {..}) {
+                return Result::Ok(());
+            }
+            self.make_node(operands, stack.pop().unwrap())?;
+        };
+    }
+
+    /// Completes inner ternary operations which already have their separator, so that
+    /// `a ? b ? c : d : e` is `a ? (b ? c : d) : e`, and checks the separator has its ternary operation.
+    fn reduce_to_ternary(&self, stack: &mut Vec<Token<T>>, operands: &mut Vec<AstNode<T>>, token: &Token<T>) -> Result<(), Token<T>> {
+        loop {
+            match stack.last() {
+                Some(Token::Operation { pos: _, val }) if val.is_separator() => {
+                    self.make_node(operands, stack.pop().unwrap())?;
+                    if stack.is_empty() {
+                        return Result::Err(token.clone());
+                    }
+                    self.make_node(operands, stack.pop().unwrap())?;
+                }
+                Some(Token::Operation { pos: _, val }) if val.separator.as_ref() == Some(&token.get_value()) => return Result::Ok(()),
+                _ => return Result::Err(token.clone()),
+            }
+        }
     }
 
     fn make_node(&self, operands: &mut Vec<AstNode<T>>, token: Token<T>) -> Result<(), Token<T>> {
@@ -325,15 +795,32 @@ impl<T: 'static + Clone> ExprCalculator<T> {
             _ => return Result::Err(token)
         };
 
-        if op.operands == 0 {
-            operands.push(AstNode::Primitive { val: (&op.calculate)(Vec::new()), token: copy })
+        if operands.len() < op.operands {
+            return Result::Err(copy);
+        }
+
+        if op.separator.is_some() {
+            let op_right = operands.pop().unwrap();
+            let op_left = operands.pop().unwrap();
+            match op_right {
+                AstNode::Binary { op: separator, p1, p2, .. } if separator.is_separator() && Some(&separator.signature) == op.separator.as_ref() => {
+                    operands.push(AstNode::Nary { op, args: vec![op_left, *p1, *p2], token: copy })
+                }
+                _ => return Result::Err(copy),
+            }
+        } else if op.operands == 0 {
+            let val = op.execute(Vec::new(), &Variables::new(), &copy)?;
+            operands.push(AstNode::Primitive { val, token: copy })
         } else if op.operands == 1 {
             let op_right = operands.pop().unwrap();
             operands.push(AstNode::Unary { op, p1: Box::new(op_right), token: copy })
-        } else {
+        } else if op.operands == 2 {
             let op_right = operands.pop().unwrap();
             let op_left = operands.pop();
             operands.push(AstNode::Binary { op, p1: Box::new(op_left.unwrap()), p2: Box::new(op_right), token: copy })
+        } else {
+            let args = operands.split_off(operands.len() - op.operands);
+            operands.push(AstNode::Nary { op, args, token: copy })
         }
 
         return Result::Ok(());
@@ -346,9 +833,13 @@ impl<T: 'static + Clone> Token<T> {
             Token::WhiteSpace { pos, val } => format!("'{}' at position {}", val, pos),
             Token::Open { pos } => format!("'(' at position {}", pos),
             Token::Close { pos } => format!("')' at position {}", pos),
+            Token::Comma { pos } => format!("',' at position {}", pos),
             Token::Primitive { pos, val: _val, original } => format!("'{}' at position {}", original, pos),
+            Token::Variable { pos, val } => format!("'{}' at position {}", val, pos),
             Token::Operation { pos, val } => format!("'{}' at position {}", val.signature.to_string(), pos),
             Token::Unknown { pos, val } => format!("'{}' at position {}", val, pos),
+            Token::Malformed { pos, val, message } => format!("'{}' at position {}: {}", val, pos, message),
+            Token::Failure { pos, val, message } => format!("'{}' at position {}: {}", val, pos, message),
         };
     }
 }
@@ -359,9 +850,13 @@ impl<T: 'static + Clone> Token<T> {
             Token::WhiteSpace { pos, .. } => *pos,
             Token::Open { pos } => *pos,
             Token::Close { pos } => *pos,
+            Token::Comma { pos } => *pos,
             Token::Primitive { pos, .. } => *pos,
+            Token::Variable { pos, .. } => *pos,
             Token::Operation { pos, .. } => *pos,
             Token::Unknown { pos, .. } => *pos,
+            Token::Malformed { pos, .. } => *pos,
+            Token::Failure { pos, .. } => *pos,
         };
     }
 
@@ -370,23 +865,121 @@ impl<T: 'static + Clone> Token<T> {
             Token::WhiteSpace { pos: _pos, val } => val.clone(),
             Token::Open { .. } => "(".to_string(),
             Token::Close { .. } => ")".to_string(),
+            Token::Comma { .. } => ",".to_string(),
             Token::Primitive { pos: _pos, val: _val, original } => original.clone(),
+            Token::Variable { pos: _pos, val } => val.clone(),
             Token::Operation { pos: _pos, val } => val.signature.clone(),
-            Token::Unknown { pos: _pos, val } => val.clone()
+            Token::Unknown { pos: _pos, val } => val.clone(),
+            Token::Malformed { pos: _pos, val, .. } => val.clone(),
+            Token::Failure { pos: _pos, val, .. } => val.clone(),
+        };
+    }
+
+    pub fn is_failure(&self) -> bool {
+        return matches!(self, Token::Failure {..});
+    }
+
+    fn to_failure(&self, message: String) -> Token<T> {
+        return Token::Failure { pos: self.get_pos(), val: self.get_value(), message };
+    }
+
+    fn starts_operand(&self) -> bool {
+        return match self {
+            Token::Primitive { .. } | Token::Variable { .. } | Token::Open { .. } => true,
+            Token::Operation { pos: _pos, val } => match val.op_type {
+                OperationType::Constant | OperationType::Prefix | OperationType::Function => true,
+                _ => false
+            },
+            _ => false
+        };
+    }
+
+    fn ends_operand(&self) -> bool {
+        return match self {
+            Token::Primitive { .. } | Token::Variable { .. } | Token::Close { .. } => true,
+            Token::Operation { pos: _pos, val } => match val.op_type {
+                OperationType::Constant | OperationType::Postfix => true,
+                _ => false
+            },
+            _ => false
         };
     }
 }
 
+impl<T: 'static + Clone> Expression<T> {
+    pub fn calculate(&self, variables: &Variables<T>) -> Result<T, Token<T>> {
+        let unbound = self.ast.find_unbound(variables);
+        if unbound.is_some() {
+            return Result::Err(unbound.unwrap());
+        }
+
+        return self.ast.calculate(variables);
+    }
+
+    /// Names of the variables used in the expression, in order of first appearance.
+    pub fn variables(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        self.ast.collect_variables(&mut names);
+
+        return names;
+    }
+}
+
 impl<T: 'static + Clone> AstNode<T> {
-    fn calculate(&self) -> Result<T, Token<T>> {
+    fn token(&self) -> &Token<T> {
+        return match self {
+            AstNode::Primitive { token, .. }
+            | AstNode::Variable { token, .. }
+            | AstNode::Unary { token, .. }
+            | AstNode::Binary { token, .. }
+            | AstNode::Nary { token, .. } => token,
+        };
+    }
+
+    fn calculate(&self, variables: &Variables<T>) -> Result<T, Token<T>> {
         let result = match self {
             AstNode::Primitive { val, .. } => val.clone(),
-            AstNode::Unary { op, p1, .. } => (&op.calculate)(vec![p1.calculate()?]),
-            AstNode::Binary { op, p1, p2, .. } => (&op.calculate)(vec![p1.calculate()?, p2.calculate()?]),
+            AstNode::Variable { name, token } => match variables.get(name) {
+                Some(val) => val.clone(),
+                None => return Result::Err(Token::Unknown { pos: token.get_pos(), val: name.clone() }),
+            },
+            AstNode::Unary { op, p1, token } => op.execute(vec![p1], variables, token)?,
+            AstNode::Binary { op, p1, p2, token } => op.execute(vec![p1, p2], variables, token)?,
+            AstNode::Nary { op, args, token } => op.execute(args.iter().collect(), variables, token)?,
         };
 
         return Result::Ok(result);
     }
+
+    /// First variable without a value, reported before calculation so lazy operations can't hide it.
+    fn find_unbound(&self, variables: &Variables<T>) -> Option<Token<T>> {
+        return match self {
+            AstNode::Primitive { .. } => None,
+            AstNode::Variable { name, token } => if variables.contains_key(name) {
+                None
+            } else {
+                Some(Token::Unknown { pos: token.get_pos(), val: name.clone() })
+            },
+            AstNode::Unary { p1, .. } => p1.find_unbound(variables),
+            AstNode::Binary { p1, p2, .. } => p1.find_unbound(variables).or_else(|| p2.find_unbound(variables)),
+            AstNode::Nary { args, .. } => args.iter().find_map(|it| it.find_unbound(variables)),
+        };
+    }
+
+    fn collect_variables(&self, names: &mut Vec<String>) {
+        match self {
+            AstNode::Primitive { .. } => (),
+            AstNode::Variable { name, .. } => if !names.contains(name) {
+                names.push(name.clone())
+            },
+            AstNode::Unary { p1, .. } => p1.collect_variables(names),
+            AstNode::Binary { p1, p2, .. } => {
+                p1.collect_variables(names);
+                p2.collect_variables(names);
+            }
+            AstNode::Nary { args, .. } => args.iter().for_each(|it| it.collect_variables(names)),
+        }
+    }
 }
 
 impl<T: 'static + Clone> Context<T> {
@@ -403,11 +996,13 @@ impl<T: 'static + Clone> Context<T> {
         self.state = State::Empty;
 
         match val {
-            ' ' => self.init_whitespace(pos),
+            it if it.is_whitespace() => self.init_whitespace(it, pos),
             '(' => self.add_token(Token::Open { pos })?,
             ')' => self.add_token(Token::Close { pos })?,
+            ',' => self.add_token(Token::Comma { pos })?,
             it if self.can_add_to_primitive(it, expr_calculator) => self.init_primitive(it, pos),
             it if expr_calculator.can_be_operation(&it.to_string()) => self.init_operation(it, pos),
+            it if is_identifier(&it.to_string()) => self.init_identifier(it, pos),
             _ => return Result::Err(Token::Unknown { pos, val: val.to_string() })
         }
 
@@ -416,7 +1011,7 @@ impl<T: 'static + Clone> Context<T> {
 
     fn collect_token(&mut self, expr_calculator: &ExprCalculator<T>) -> Result<(), Token<T>> {
         let token = match self.state {
-            State::Operation => {
+            State::Operation | State::Identifier => {
                 let op = self.value.clone();
                 let op_type = if self.suitable_for_prefix(&op, expr_calculator) {
                     OperationType::Prefix
@@ -426,15 +1021,23 @@ impl<T: 'static + Clone> Context<T> {
                     OperationType::Infix
                 } else if self.suitable_for_function(&op, expr_calculator) {
                     OperationType::Function
+                } else if let Some(op_type) = self.implicit_operand_type(&op, expr_calculator) {
+                    op_type
                 } else if expr_calculator.make_by_type(&op, OperationType::Constant).is_some() {
                     OperationType::Constant
+                } else if is_identifier(&op) {
+                    return self.add_token(Token::Variable { pos: self.pos, val: op });
                 } else {
                     return Result::Err(Token::Unknown { pos: self.pos, val: self.value.clone() });
                 };
 
-                let operation = expr_calculator.make_by_type(&op, op_type);
+                let mut operation = expr_calculator.make_by_type(&op, op_type).unwrap().clone();
+                if operation.separator.is_none() {
+                    // keeps the spelling used in the input, so errors quote an alias as typed
+                    operation.signature = op;
+                }
 
-                Token::Operation { pos: self.pos, val: Box::new(operation.unwrap().clone()) }
+                Token::Operation { pos: self.pos, val: Box::new(operation) }
             }
             State::WhiteSpace => self.to_whitespace_token(),
             State::Empty => return Result::Ok(()),
@@ -483,18 +1086,31 @@ impl<T: 'static + Clone> Context<T> {
         self.add_symbol(value)
     }
 
+    fn can_add_to_identifier(&self, char: char) -> bool {
+        let mut test = String::from(&self.value);
+        test.push(char);
+
+        return is_identifier(&test);
+    }
+
+    fn mutate_to_identifier(&mut self, value: char) {
+        self.state = State::Identifier;
+        self.add_symbol(value)
+    }
+
     fn is_suitable_for_current_state(&self, char: char, expr_calculator: &ExprCalculator<T>) -> bool {
         return match self.state {
             State::Empty => false,
             State::Primitive => self.can_add_to_primitive(char, expr_calculator),
             State::Operation => self.can_add_to_operation(char, expr_calculator),
-            State::WhiteSpace => char == ' '
+            State::Identifier => self.can_add_to_identifier(char),
+            State::WhiteSpace => char.is_whitespace()
         };
     }
 
-    fn init_whitespace(&mut self, pos: usize) {
+    fn init_whitespace(&mut self, val: char, pos: usize) {
         self.state = State::WhiteSpace;
-        self.value = String::from(' ');
+        self.value = String::from(val);
         self.pos = pos;
     }
 
@@ -510,6 +1126,12 @@ impl<T: 'static + Clone> Context<T> {
         self.pos = pos;
     }
 
+    fn init_identifier(&mut self, val: char, pos: usize) {
+        self.state = State::Identifier;
+        self.value = String::from(val);
+        self.pos = pos;
+    }
+
     fn add_symbol(&mut self, symbol: char) {
         self.value.push(symbol)
     }
@@ -521,8 +1143,14 @@ impl<T: 'static + Clone> Context<T> {
     fn to_primitive(&self, expr_calculator: &ExprCalculator<T>) -> Result<Token<T>, Token<T>> {
         let val = expr_calculator.handler.from_string(&self.value);
 
+        if val.is_err() && is_identifier(&self.value) {
+            return Result::Ok(Token::Variable { pos: self.pos, val: self.value.clone() });
+        }
         if val.is_err() {
-            return Result::Err(Token::Unknown { pos: self.pos, val: self.value.clone() });
+            return Result::Err(match expr_calculator.handler.explain(&self.value) {
+                Some(message) => Token::Malformed { pos: self.pos, val: self.value.clone(), message },
+                None => Token::Unknown { pos: self.pos, val: self.value.clone() },
+            });
         }
 
         return Result::Ok(Token::Primitive { pos: self.pos, val: val.unwrap(), original: self.value.clone() });
@@ -543,7 +1171,7 @@ impl<T: 'static + Clone> Context<T> {
         }
 
         return match last.unwrap() {
-            Token::Open { .. } => true,
+            Token::Open { .. } | Token::Comma { .. } => true,
             Token::Operation { pos: _pos, val } => match val.op_type {
                 OperationType::Constant => false,
                 _ => true
@@ -564,7 +1192,7 @@ impl<T: 'static + Clone> Context<T> {
 
         return match last.unwrap() {
             Token::Close { .. } => true,
-            Token::Primitive { .. } => true,
+            Token::Primitive { .. } | Token::Variable { .. } => true,
             Token::Operation { pos: _pos, val } => match val.op_type {
                 OperationType::Constant | OperationType::Postfix => true,
                 _ => false
@@ -585,7 +1213,7 @@ impl<T: 'static + Clone> Context<T> {
 
         return match last.unwrap() {
             Token::Close { .. } => true,
-            Token::Primitive { .. } => true,
+            Token::Primitive { .. } | Token::Variable { .. } => true,
             Token::Operation { pos: _pos, val } => match val.op_type {
                 OperationType::Constant | OperationType::Postfix => true,
                 _ => false
@@ -594,6 +1222,16 @@ impl<T: 'static + Clone> Context<T> {
         };
     }
 
+    /// Function or prefix operation starting the right operand of an implicit multiplication, e.g. `sin` of `2sin(x)`.
+    fn implicit_operand_type(&self, value: &String, expr_calculator: &ExprCalculator<T>) -> Option<OperationType> {
+        if expr_calculator.implicit_operation.is_none() || !self.take_last().map_or(false, |it| it.ends_operand()) {
+            return None;
+        }
+
+        return vec![OperationType::Function, OperationType::Prefix].into_iter()
+            .find(|op_type| expr_calculator.make_by_type(value, op_type.clone()).is_some());
+    }
+
     fn suitable_for_function(&self, value: &String, expr_calculator: &ExprCalculator<T>) -> bool {
         if expr_calculator.make_by_type(value, OperationType::Function).is_none() {
             return false;
@@ -605,7 +1243,7 @@ impl<T: 'static + Clone> Context<T> {
         }
 
         return match last.unwrap() {
-            Token::Open { .. } => true,
+            Token::Open { .. } | Token::Comma { .. } => true,
             Token::Operation { pos: _pos, val } => match val.op_type {
                 OperationType::Infix | OperationType::Prefix => true,
                 _ => false
@@ -616,18 +1254,45 @@ impl<T: 'static + Clone> Context<T> {
 }
 
 impl<T: 'static + Clone> Operation<T> {
+    fn execute(&self, operands: Vec<&AstNode<T>>, variables: &Variables<T>, token: &Token<T>) -> Result<T, Token<T>> {
+        return match &self.calculate {
+            Executor::Eager(calculate) => {
+                let values = operands.iter().map(|it| it.calculate(variables)).collect::<Result<Vec<T>, Token<T>>>()?;
+                calculate(values).map_err(|message| token.to_failure(message))
+            }
+            Executor::Lazy(calculate) => {
+                let failure = RefCell::new(None);
+                let thunks: Vec<Thunk<T>> = operands.iter().map(|node| Thunk { node, variables, failure: &failure }).collect();
+                let result = calculate(&thunks);
+                result.map_err(|message| failure.take().unwrap_or_else(|| token.to_failure(message)))
+            }
+            Executor::Separator => Result::Err(token.to_failure(format!("'{}' without its ternary operation", self.signature))),
+        };
+    }
+
+    /// Syntax of the operation, followed by the syntax of its aliases.
     pub fn pretty(&self) -> String {
+        return self.spellings().map(|it| self.pretty_as(it)).collect::<Vec<String>>().join(", ");
+    }
+
+    fn pretty_as(&self, signature: &String) -> String {
+        let space = if is_identifier(signature) { " " } else { "" };
+
         return match self.op_type.clone() {
-            OperationType::Constant => self.signature.clone(),
-            OperationType::Prefix => format!("{}x", &self.signature),
-            OperationType::Postfix => format!("x{}", &self.signature),
-            OperationType::Infix => format!("x{}y", &self.signature),
+            OperationType::Constant => signature.clone(),
+            OperationType::Prefix => format!("{}{}x", signature, space),
+            OperationType::Postfix => format!("x{}{}", space, signature),
+            OperationType::Infix => match &self.separator {
+                Some(separator) => format!("x {} y {} z", signature, separator),
+                None => format!("x{}{}{}y", space, signature, space),
+            },
             OperationType::Function => format!(
                 "{}({})",
-                &self.signature,
+                signature,
                 vec!["x", "y", "z", "a", "b", "c", "..."]
                     .iter()
-                    .take(usize::from(self.operands))
+                    .take(self.operands)
+                    .chain(if self.variadic { Some(&"...") } else { None })
                     .map(|it| { it.to_string() })
                     .collect::<Vec<String>>()
                     .join(", ")
@@ -639,7 +1304,30 @@ impl<T: 'static + Clone> Operation<T> {
         return self.description.clone();
     }
 
+    /// The signature and its aliases.
+    pub fn spellings(&self) -> impl Iterator<Item = &String> {
+        return std::iter::once(&self.signature).chain(self.aliases.iter());
+    }
+
     pub fn priority(&self) -> u8 {
         return self.priority;
     }
-}
\ No newline at end of file
+
+    /// Second part of a ternary operation, listed together with it.
+    pub fn is_separator(&self) -> bool {
+        return matches!(self.calculate, Executor::Separator);
+    }
+}
+
+/// Variable names: a letter or `_` followed by letters, digits or `_`.
+pub fn is_identifier(value: &str) -> bool {
+    let mut chars = value.chars();
+
+    return chars.next().map_or(false, |it| it.is_alphabetic() || it == '_')
+        && chars.all(|it| it.is_alphanumeric() || it == '_');
+}
+
+/// Whether `rest` starts with a line break, `\n` or `\r\n`.
+fn is_line_end(rest: &[char]) -> bool {
+    return rest.first() == Some(&'\n') || (rest.first() == Some(&'\r') && rest.get(1) == Some(&'\n'));
+}
@@ -1,51 +1,107 @@
-use crate::ast::{ExprCalculator, HIGH_ORDER, LOW_ORDER, PrimitiveHandler};
+use crate::ast::{ExprCalculator, HIGHEST_ORDER, HIGH_ORDER, LOWEST_ORDER, LOW_ORDER, MEDIUM_ORDER, PrimitiveHandler};
+
+/// Priorities from the loosest to the tightest binding: `<->`, `->`, OR/NOR, XOR, AND/NAND, NOT.
+const EQUIVALENCE_ORDER: u8 = LOWEST_ORDER;
+const IMPLICATION_ORDER: u8 = LOWEST_ORDER + 5;
+const OR_ORDER: u8 = LOW_ORDER;
+const XOR_ORDER: u8 = LOW_ORDER + 5;
+const AND_ORDER: u8 = MEDIUM_ORDER;
+const NOT_ORDER: u8 = HIGH_ORDER;
 
 struct BoolHandler {}
 
 impl PrimitiveHandler<bool> for BoolHandler {
     fn from_string(&self, input: &String) -> Result<bool, ()> {
         return match input.to_lowercase().as_str() {
-            "true" => Result::Ok(true),
-            "false" => Result::Ok(false),
+            "true" | "1" => Result::Ok(true),
+            "false" | "0" => Result::Ok(false),
             _ => Result::Err(()),
         };
     }
 
     fn can_start_with(&self, input: String) -> bool {
-        return "true".starts_with(&input) || "false".starts_with(&input);
+        return "true".starts_with(&input) || "false".starts_with(&input) || input == "1" || input == "0";
     }
 }
 
 pub fn boolean_calculator() -> ExprCalculator<bool> {
     let mut result = ExprCalculator::<bool>::new(Box::new(BoolHandler {}));
 
-    result.add_infix(
-        "|".to_lowercase(),
-        "OR".to_string(),
-        Box::new(|op1, op2| { op1 | op2 }),
-        LOW_ORDER,
+    for signature in vec!["|", "or"] {
+        result.add_lazy_infix(
+            signature.to_string(),
+            "OR, y is skipped when x is true".to_string(),
+            Box::new(|operands| { Result::Ok(operands[0].get()? || operands[1].get()?) }),
+            OR_ORDER,
+        );
+    }
+
+    for signature in vec!["&", "and"] {
+        result.add_lazy_infix(
+            signature.to_string(),
+            "AND, y is skipped when x is false".to_string(),
+            Box::new(|operands| { Result::Ok(operands[0].get()? && operands[1].get()?) }),
+            AND_ORDER,
+        );
+    }
+
+    for signature in vec!["^", "xor"] {
+        result.add_infix(
+            signature.to_string(),
+            "XOR".to_string(),
+            Box::new(|op1, op2| { op1 ^ op2 }),
+            XOR_ORDER,
+        );
+    }
+
+    result.add_lazy_infix(
+        "nand".to_string(),
+        "NAND, !(x & y)".to_string(),
+        Box::new(|operands| { Result::Ok(!(operands[0].get()? && operands[1].get()?)) }),
+        AND_ORDER,
     );
 
-    result.add_infix(
-        "&".to_lowercase(),
-        "AND".to_string(),
-        Box::new(|op1, op2| { op1 & op2 }),
-        LOW_ORDER,
+    result.add_lazy_infix(
+        "nor".to_string(),
+        "NOR, !(x | y)".to_string(),
+        Box::new(|operands| { Result::Ok(!(operands[0].get()? || operands[1].get()?)) }),
+        OR_ORDER,
+    );
+
+    result.add_right_infix(
+        "->".to_string(),
+        "Implication, !x | y".to_string(),
+        Box::new(|op1, op2| { !op1 | op2 }),
+        IMPLICATION_ORDER,
     );
 
     result.add_infix(
-        "^".to_lowercase(),
-        "XOR".to_string(),
-        Box::new(|op1, op2| { op1 ^ op2 }),
-        LOW_ORDER,
+        "<->".to_string(),
+        "Equivalence".to_string(),
+        Box::new(|op1, op2| { op1 == op2 }),
+        EQUIVALENCE_ORDER,
     );
 
-    result.add_prefix(
-        "!".to_lowercase(),
-        "NOT".to_string(),
-        Box::new(|op1| { !op1 }),
-        HIGH_ORDER,
+    result.add_lazy_function(
+        "if".to_string(),
+        "y if x is true, otherwise z".to_string(),
+        Box::new(|operands| { if operands[0].get()? { operands[1].get() } else { operands[2].get() } }),
+        3,
+        HIGHEST_ORDER,
     );
 
+    for signature in vec!["!", "not"] {
+        result.add_prefix(
+            signature.to_string(),
+            "NOT".to_string(),
+            Box::new(|op1| { !op1 }),
+            NOT_ORDER,
+        );
+    }
+
+    for (signature, alias) in vec![("!", "¬"), ("&", "∧"), ("|", "∨"), ("^", "⊕"), ("->", "→"), ("<->", "↔")] {
+        result.add_alias(signature, alias);
+    }
+
     return result;
-}
\ No newline at end of file
+}
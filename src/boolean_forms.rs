@@ -0,0 +1,266 @@
+use std::collections::HashSet;
+
+use crate::ast::{ExprCalculator, Token, Variables};
+use crate::truth_table::{assignment, TruthTable, MAX_VARIABLES};
+
+/// Quine–McCluskey gets slow quickly, so minimization is limited to small expressions.
+pub const MAX_MINIMIZE_VARIABLES: usize = 10;
+
+/// Products of Petrick's method compared at most, beyond them the cover is completed greedily.
+const MAX_PETRICK_PRODUCTS: usize = 500;
+
+/// Product (or sum) term: `Some(true)` for `x`, `Some(false)` for `!x`, `None` when the variable is absent.
+type Term = Vec<Option<bool>>;
+
+/// Canonical disjunctive normal form: OR of all minterms.
+pub fn dnf(table: &TruthTable) -> String {
+    let terms: Vec<Term> = table.minterms().iter()
+        .map(|index| assignment(*index, table.variables.len()).into_iter().map(Some).collect())
+        .collect();
+
+    return sum_of_products(&table.variables, &terms);
+}
+
+/// Canonical conjunctive normal form: AND of all maxterms.
+pub fn cnf(table: &TruthTable) -> String {
+    let clauses: Vec<String> = table.rows.iter()
+        .filter(|row| !row.1)
+        .map(|row| join_literals(&table.variables, &row.0.iter().map(|it| Some(!*it)).collect(), " | "))
+        .collect();
+
+    if clauses.is_empty() {
+        return "true".to_string();
+    }
+    if table.variables.is_empty() {
+        return "false".to_string();
+    }
+
+    return join_terms(clauses, " & ");
+}
+
+/// Minimal sum of products found with the Quine–McCluskey method.
+pub fn minimize(table: &TruthTable) -> Result<String, String> {
+    if table.variables.len() > MAX_MINIMIZE_VARIABLES {
+        return Result::Err(format!("minimization supports at most {} variables", MAX_MINIMIZE_VARIABLES));
+    }
+
+    let minterms = table.minterms();
+    let implicants = prime_implicants(&minterms, table.variables.len());
+    let mut cover = minimal_cover(&minterms, &implicants, table.variables.len());
+    cover.sort_by_key(|term| term.iter().map(|literal| literal.map_or(2, |it| !it as u8)).collect::<Vec<u8>>());
+
+    return Result::Ok(sum_of_products(&table.variables, &cover));
+}
+
+/// Assignment making the expression true, if there is one.
+pub fn satisfying_assignment(table: &TruthTable) -> Option<String> {
+    return table.rows.iter()
+        .find(|row| row.1)
+        .map(|row| format_assignment(&table.variables, &row.0));
+}
+
+/// Assignment making the expression false, if there is one.
+pub fn falsifying_assignment(table: &TruthTable) -> Option<String> {
+    return table.rows.iter()
+        .find(|row| !row.1)
+        .map(|row| format_assignment(&table.variables, &row.0));
+}
+
+/// Compares two expressions over the union of their variables.
+/// Returns `None` when they are equivalent, otherwise an assignment where they differ.
+pub fn counterexample(calculator: &ExprCalculator<bool>, left: &str, right: &str) -> Result<Option<String>, (String, Token<bool>)> {
+    let left_expression = calculator.compile(left).map_err(|it| (left.to_string(), it))?;
+    let right_expression = calculator.compile(right).map_err(|it| (right.to_string(), it))?;
+
+    let mut variables = left_expression.variables();
+    variables.extend(right_expression.variables().into_iter().filter(|it| !variables.contains(it)).collect::<Vec<String>>());
+    variables.sort();
+
+    if variables.len() > MAX_VARIABLES {
+        return Result::Err((left.to_string(), Token::Failure {
+            pos: 0,
+            val: left.to_string(),
+            message: format!("too many variables: {}, at most {} supported", variables.len(), MAX_VARIABLES),
+        }));
+    }
+
+    for index in 0..1usize << variables.len() {
+        let values = assignment(index, variables.len());
+        let bindings: Variables<bool> = variables.iter().cloned().zip(values.iter().cloned()).collect();
+        let left_result = left_expression.calculate(&bindings).map_err(|it| (left.to_string(), it))?;
+        let right_result = right_expression.calculate(&bindings).map_err(|it| (right.to_string(), it))?;
+
+        if left_result != right_result {
+            return Result::Ok(Some(format!(
+                "{} (left={}, right={})",
+                format_assignment(&variables, &values),
+                left_result as u8,
+                right_result as u8
+            )));
+        }
+    }
+
+    return Result::Ok(None);
+}
+
+/// Cubes are merged as bit sets: `values` of the present variables and `absent` ones, in the bit order of `assignment`.
+/// A cube is merged with the one differing in a single present variable, found by lookup instead of comparing pairs.
+fn prime_implicants(minterms: &Vec<usize>, count: usize) -> Vec<Term> {
+    let mut current: HashSet<(usize, usize)> = minterms.iter().map(|index| (*index, 0)).collect();
+    let mut primes: Vec<(usize, usize)> = Vec::new();
+
+    while !current.is_empty() {
+        let mut next: HashSet<(usize, usize)> = HashSet::new();
+        let mut combined: HashSet<(usize, usize)> = HashSet::new();
+        for (values, absent) in current.iter() {
+            for bit in (0..count).map(|it| 1 << it) {
+                if absent & bit != 0 || values & bit != 0 || !current.contains(&(values | bit, *absent)) {
+                    continue;
+                }
+                combined.insert((*values, *absent));
+                combined.insert((values | bit, *absent));
+                next.insert((*values, absent | bit));
+            }
+        }
+
+        primes.extend(current.iter().filter(|it| !combined.contains(it)));
+        current = next;
+    }
+    // sets have no order, the cover shouldn't depend on it
+    primes.sort();
+
+    return primes.into_iter()
+        .map(|(values, absent)| (0..count).rev().map(|bit| if absent >> bit & 1 == 1 { None } else { Some(values >> bit & 1 == 1) }).collect())
+        .collect();
+}
+
+fn covers(term: &Term, minterm: usize, count: usize) -> bool {
+    return term.iter().zip(assignment(minterm, count)).all(|(literal, value)| literal.map_or(true, |it| it == value));
+}
+
+/// Smallest set of implicants covering all minterms, ties broken by the number of literals.
+/// Essential implicants are taken first, the rest of the minterms are covered with Petrick's method.
+/// When its products grow beyond `MAX_PETRICK_PRODUCTS` the cover is completed greedily, so it may not be minimal.
+fn minimal_cover(minterms: &Vec<usize>, implicants: &Vec<Term>, count: usize) -> Vec<Term> {
+    // indexes of the implicants covering each minterm
+    let choices: Vec<Vec<usize>> = minterms.iter()
+        .map(|minterm| (0..implicants.len()).filter(|index| covers(&implicants[*index], *minterm, count)).collect())
+        .collect();
+
+    let mut chosen: Vec<usize> = Vec::new();
+    for options in choices.iter().filter(|it| it.len() == 1) {
+        if !chosen.contains(&options[0]) {
+            chosen.push(options[0]);
+        }
+    }
+    let remaining: Vec<&Vec<usize>> = choices.iter().filter(|options| !options.iter().any(|it| chosen.contains(it))).collect();
+
+    let rest = match petrick(&remaining, implicants) {
+        Some(rest) => rest,
+        None => greedy_cover(&remaining, implicants),
+    };
+    chosen.extend(rest);
+
+    return chosen.into_iter().map(|index| implicants[index].clone()).collect();
+}
+
+/// Cheapest product of Petrick's method, `None` when there are too many products to compare.
+fn petrick(remaining: &[&Vec<usize>], implicants: &Vec<Term>) -> Option<Vec<usize>> {
+    let mut products: Vec<Vec<usize>> = vec![Vec::new()];
+    for options in remaining {
+        let mut next: Vec<Vec<usize>> = Vec::new();
+        for product in products.iter() {
+            if options.iter().any(|it| product.contains(it)) {
+                next.push(product.clone());
+                continue;
+            }
+            for option in options.iter() {
+                let mut extended = product.clone();
+                extended.push(*option);
+                extended.sort();
+                next.push(extended);
+            }
+        }
+        next.sort_by_key(|it| it.len());
+        next.dedup();
+
+        // a product containing another one is never cheaper: X + XY = X
+        products = Vec::new();
+        for product in next {
+            if !products.iter().any(|kept: &Vec<usize>| kept.iter().all(|it| product.contains(it))) {
+                products.push(product);
+            }
+        }
+        if products.len() > MAX_PETRICK_PRODUCTS {
+            return None;
+        }
+    }
+
+    return products.into_iter().min_by_key(|product| cost(&product.iter().map(|index| implicants[*index].clone()).collect()));
+}
+
+/// Repeatedly takes the implicant covering most of the uncovered minterms, then the one with fewer literals.
+fn greedy_cover(remaining: &[&Vec<usize>], implicants: &Vec<Term>) -> Vec<usize> {
+    let mut uncovered: Vec<&Vec<usize>> = remaining.to_vec();
+    let mut chosen: Vec<usize> = Vec::new();
+    while !uncovered.is_empty() {
+        let best = (0..implicants.len())
+            .max_by_key(|index| {
+                let covered = uncovered.iter().filter(|options| options.contains(index)).count();
+                let literals = implicants[*index].iter().filter(|literal| literal.is_some()).count();
+                (covered, std::cmp::Reverse(literals))
+            })
+            .unwrap();
+        chosen.push(best);
+        uncovered.retain(|options| !options.contains(&best));
+    }
+
+    return chosen;
+}
+
+fn cost(terms: &Vec<Term>) -> (usize, usize) {
+    return (terms.len(), terms.iter().map(|it| it.iter().filter(|literal| literal.is_some()).count()).sum());
+}
+
+fn sum_of_products(variables: &Vec<String>, terms: &Vec<Term>) -> String {
+    if terms.is_empty() {
+        return "false".to_string();
+    }
+
+    return join_terms(terms.iter().map(|it| join_literals(variables, it, " & ")).collect(), " | ");
+}
+
+fn join_literals(variables: &Vec<String>, term: &Term, separator: &str) -> String {
+    let literals: Vec<String> = variables.iter().zip(term)
+        .filter_map(|(name, literal)| literal.map(|it| if it { name.clone() } else { format!("!{}", name) }))
+        .collect();
+
+    if literals.is_empty() {
+        return "true".to_string();
+    }
+
+    return literals.join(separator);
+}
+
+/// Joins terms, wrapping compound terms in parentheses so the result can be parsed back.
+fn join_terms(terms: Vec<String>, separator: &str) -> String {
+    if terms.len() == 1 {
+        return terms[0].clone();
+    }
+
+    return terms.iter()
+        .map(|it| if it.contains(' ') { format!("({})", it) } else { it.clone() })
+        .collect::<Vec<String>>()
+        .join(separator);
+}
+
+fn format_assignment(variables: &Vec<String>, values: &Vec<bool>) -> String {
+    if variables.is_empty() {
+        return "no variables".to_string();
+    }
+
+    return variables.iter().zip(values)
+        .map(|(name, value)| format!("{}={}", name, *value as u8))
+        .collect::<Vec<String>>()
+        .join(", ");
+}
@@ -0,0 +1,95 @@
+use std::cmp::Ordering;
+
+use crate::ast::{ExprCalculator, HIGHEST_ORDER, LOWEST_ORDER, Thunk};
+use crate::format::{Format, NumberFormat};
+use crate::operation_executor::FallibleBinaryOperationExecutor;
+
+/// Comparisons bind looser than arithmetic, conditional operations are the loosest.
+pub const COMPARISON_ORDER: u8 = LOWEST_ORDER - 2;
+pub const EQUALITY_ORDER: u8 = LOWEST_ORDER - 4;
+const CONDITIONAL_ORDER: u8 = LOWEST_ORDER - 6;
+
+/// Order of two values, `None` when they are unordered such as NaN. An error when they can't be compared.
+pub type Order<T> = fn(&T, &T) -> Result<Option<Ordering>, String>;
+/// Equality of two values, an error when they can't be compared.
+pub type Equality<T> = fn(&T, &T) -> Result<bool, String>;
+
+/// Adds `<`, `<=`, `>`, `>=`, `==` and `!=`, which give `from_bool(..)`.
+pub fn add_comparisons<T: 'static + Clone + Format, F: 'static + Fn(bool) -> T + Clone>(
+    calculator: &mut ExprCalculator<T>,
+    order: Order<T>,
+    equals: Equality<T>,
+    from_bool: F,
+) {
+    let default = NumberFormat::default();
+    let (yes, no) = (from_bool(true).format(&default), from_bool(false).format(&default));
+    let describe = |condition: &str| { format!("{} if x {} y, otherwise {}", yes, condition, no) };
+
+    let less: fn(Ordering) -> bool = |it| { it == Ordering::Less };
+    let less_or_equal: fn(Ordering) -> bool = |it| { it != Ordering::Greater };
+    let greater: fn(Ordering) -> bool = |it| { it == Ordering::Greater };
+    let greater_or_equal: fn(Ordering) -> bool = |it| { it != Ordering::Less };
+    calculator.add_fallible_infix("<".to_string(), describe("is less than"), comparison(order, less, from_bool.clone()), COMPARISON_ORDER);
+    calculator.add_fallible_infix("<=".to_string(), describe("is less than or equal to"), comparison(order, less_or_equal, from_bool.clone()), COMPARISON_ORDER);
+    calculator.add_fallible_infix(">".to_string(), describe("is greater than"), comparison(order, greater, from_bool.clone()), COMPARISON_ORDER);
+    calculator.add_fallible_infix(">=".to_string(), describe("is greater than or equal to"), comparison(order, greater_or_equal, from_bool.clone()), COMPARISON_ORDER);
+    calculator.add_fallible_infix("==".to_string(), describe("equals"), equality(equals, true, from_bool.clone()), EQUALITY_ORDER);
+    calculator.add_fallible_infix("!=".to_string(), describe("doesn't equal"), equality(equals, false, from_bool), EQUALITY_ORDER);
+}
+
+/// Adds comparisons and conditional operations to a numeric calculator:
+/// comparisons give `from_bool(..)`, conditions are true when `is_true` says so.
+pub fn add_conditions<T: 'static + Clone + PartialOrd + Format, F: 'static + Fn(bool) -> T + Clone>(
+    calculator: &mut ExprCalculator<T>,
+    is_true: fn(&T) -> bool,
+    from_bool: F,
+) {
+    add_comparisons(calculator, |op1, op2| { Result::Ok(op1.partial_cmp(op2)) }, |op1, op2| { Result::Ok(op1 == op2) }, from_bool);
+    calculator.add_lazy_function(
+        "if".to_string(),
+        "y if x is not 0, otherwise z".to_string(),
+        Box::new(move |operands| { if condition(&operands[0], is_true)? { operands[1].get() } else { operands[2].get() } }),
+        3,
+        HIGHEST_ORDER
+    );
+    calculator.add_lazy_variadic_function(
+        "piecewise".to_string(),
+        "Value after the first non-0 condition, otherwise the last argument".to_string(),
+        Box::new(move |operands| {
+            if operands.len() % 2 == 0 {
+                return Result::Err("expected pairs of condition and value followed by a default value".to_string());
+            }
+            for pair in operands.chunks(2).filter(|it| it.len() == 2) {
+                if condition(&pair[0], is_true)? {
+                    return pair[1].get();
+                }
+            }
+            return operands[operands.len() - 1].get();
+        }),
+        3,
+        HIGHEST_ORDER
+    );
+    calculator.add_ternary(
+        "?".to_string(),
+        ":".to_string(),
+        "y if x is not 0, otherwise z".to_string(),
+        Box::new(move |operands| { if condition(&operands[0], is_true)? { operands[1].get() } else { operands[2].get() } }),
+        CONDITIONAL_ORDER,
+    );
+}
+
+fn comparison<T: 'static + Clone, F: 'static + Fn(bool) -> T + Clone>(
+    order: Order<T>,
+    holds: fn(Ordering) -> bool,
+    from_bool: F,
+) -> Box<dyn FallibleBinaryOperationExecutor<T>> {
+    return Box::new(move |op1, op2| { Result::Ok(from_bool(order(&op1, &op2)?.map_or(false, holds))) });
+}
+
+fn equality<T: 'static + Clone, F: 'static + Fn(bool) -> T + Clone>(equals: Equality<T>, expected: bool, from_bool: F) -> Box<dyn FallibleBinaryOperationExecutor<T>> {
+    return Box::new(move |op1, op2| { Result::Ok(from_bool(equals(&op1, &op2)? == expected)) });
+}
+
+fn condition<T: 'static + Clone>(operand: &Thunk<T>, is_true: fn(&T) -> bool) -> Result<bool, String> {
+    return Result::Ok(is_true(&operand.get()?));
+}
@@ -0,0 +1,209 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use rug::{Integer, Rational};
+use strum_macros::{Display, EnumString};
+
+use crate::format::{group_thousands, Digits, Format, NumberFormat};
+use crate::literal::Literal;
+
+/// Rounding rule applied when a value has to lose decimal places.
+#[derive(Clone, Copy, PartialEq, Debug, Display, EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Rounding {
+    /// Ties go to the even neighbour (banker's rounding): 0.125 -> 0.12, 0.135 -> 0.14
+    HalfEven,
+    /// Ties go away from zero: 0.125 -> 0.13
+    HalfUp,
+}
+
+/// Largest magnitude of the exponent of `Decimal::pow`, larger powers would take too long to be exact.
+pub const MAX_EXPONENT: u32 = 100_000;
+
+/// Exact base-10 number `units * 10^-scale`.
+#[derive(Clone, Debug)]
+pub struct Decimal {
+    units: Integer,
+    scale: u32,
+}
+
+impl Decimal {
+    pub fn new(units: Integer, scale: u32) -> Decimal {
+        return Decimal { units, scale };
+    }
+
+    /// Keeps the digits as written: `1.50` has scale 2 and `1.5e3` has scale 0.
+    pub fn from_literal(literal: &Literal) -> Decimal {
+        let (mantissa, exponent) = literal.to_mantissa_and_exponent();
+        if exponent >= 0 {
+            return Decimal { units: mantissa * pow10(exponent as u32), scale: 0 };
+        }
+
+        return Decimal { units: mantissa, scale: (-exponent) as u32 };
+    }
+
+    pub fn is_zero(&self) -> bool {
+        return self.units.cmp0() == Ordering::Equal;
+    }
+
+    pub fn abs(&self) -> Decimal {
+        return Decimal { units: self.units.clone().abs(), scale: self.scale };
+    }
+
+    pub fn neg(&self) -> Decimal {
+        return Decimal { units: -self.units.clone(), scale: self.scale };
+    }
+
+    pub fn add(&self, other: &Decimal) -> Decimal {
+        let scale = self.scale.max(other.scale);
+
+        return Decimal { units: self.units_at(scale) + other.units_at(scale), scale };
+    }
+
+    pub fn sub(&self, other: &Decimal) -> Decimal {
+        return self.add(&other.neg());
+    }
+
+    pub fn mul(&self, other: &Decimal) -> Decimal {
+        return Decimal { units: self.units.clone() * &other.units, scale: self.scale + other.scale };
+    }
+
+    /// Quotient rounded to `scale` decimal places.
+    pub fn div(&self, other: &Decimal, scale: u32, rounding: Rounding) -> Result<Decimal, String> {
+        if other.is_zero() {
+            return Result::Err("division by zero".to_string());
+        }
+
+        let numerator = self.units.clone() * pow10(other.scale + scale);
+        let denominator = other.units.clone() * pow10(self.scale);
+
+        return Result::Ok(Decimal { units: divide(numerator, denominator, rounding), scale });
+    }
+
+    /// Integer power by squaring, the exponent is at most `MAX_EXPONENT` in magnitude.
+    /// Negative exponents are computed as a division rounded to `scale`.
+    pub fn pow(&self, exponent: &Decimal, scale: u32, rounding: Rounding) -> Result<Decimal, String> {
+        let exponent = exponent.to_integer().ok_or("exponent must be an integer".to_string())?;
+        let count = exponent.clone().abs().to_u32().filter(|it| *it <= MAX_EXPONENT).ok_or("exponent is too large".to_string())?;
+        if self.scale as u64 * count as u64 > u32::MAX as u64 {
+            return Result::Err("exponent is too large".to_string());
+        }
+
+        let mut result = Decimal::new(Integer::from(1), 0);
+        let mut square = self.clone();
+        let mut rest = count;
+        while rest > 0 {
+            if rest % 2 == 1 {
+                result = result.mul(&square);
+            }
+            rest /= 2;
+            if rest > 0 {
+                square = square.mul(&square);
+            }
+        }
+
+        if exponent.cmp0() == Ordering::Less {
+            return Decimal::new(Integer::from(1), 0).div(&result, scale, rounding);
+        }
+
+        return Result::Ok(result);
+    }
+
+    pub fn round(&self, scale: u32, rounding: Rounding) -> Decimal {
+        if scale >= self.scale {
+            return Decimal { units: self.units_at(scale), scale };
+        }
+
+        return Decimal { units: divide(self.units.clone(), pow10(self.scale - scale), rounding), scale };
+    }
+
+    /// Same as `Display`, with a `,` between every three digits of the integer part.
+    pub fn to_grouped_string(&self) -> String {
+        let plain = self.to_string();
+        let (sign, unsigned) = if plain.starts_with('-') { ("-", &plain[1..]) } else { ("", plain.as_str()) };
+        let (integer, fraction) = match unsigned.find('.') {
+            Some(dot) => (&unsigned[..dot], &unsigned[dot..]),
+            None => (unsigned, ""),
+        };
+
+        return format!("{}{}{}", sign, group_thousands(integer), fraction);
+    }
+
+    fn to_integer(&self) -> Option<Integer> {
+        let (quotient, remainder) = self.units.clone().div_rem(pow10(self.scale));
+        if remainder.cmp0() != Ordering::Equal {
+            return None;
+        }
+
+        return Some(quotient);
+    }
+
+    fn units_at(&self, scale: u32) -> Integer {
+        return self.units.clone() * pow10(scale - self.scale);
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let digits = self.units.clone().abs().to_string();
+        let sign = if self.units.cmp0() == Ordering::Less { "-" } else { "" };
+        let scale = self.scale as usize;
+
+        if scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+
+        let padded = format!("{:0>1$}", digits, scale + 1);
+        let (integer, fraction) = padded.split_at(padded.len() - scale);
+
+        return write!(f, "{}{}.{}", sign, integer, fraction);
+    }
+}
+
+impl Format for Decimal {
+    /// Keeps the scale, `0.30`, unless a format is asked for.
+    fn format(&self, format: &NumberFormat) -> String {
+        if format.is_default() {
+            return self.to_string();
+        }
+        if format.base != 10 {
+            return Rational::from((self.units.clone(), pow10(self.scale))).format(format);
+        }
+
+        let digits = self.units.clone().abs().to_string();
+        let exponent = digits.len() as i32 - self.scale as i32;
+
+        return Digits { negative: self.units.cmp0() == Ordering::Less, digits, exponent }.format(format);
+    }
+
+    fn type_name(&self) -> &'static str {
+        return "decimal";
+    }
+
+    fn to_input(&self) -> String {
+        return self.to_string();
+    }
+}
+
+fn pow10(exponent: u32) -> Integer {
+    return Integer::from(Integer::u_pow_u(10, exponent));
+}
+
+fn divide(numerator: Integer, denominator: Integer, rounding: Rounding) -> Integer {
+    let negative = numerator.cmp0() != denominator.cmp0();
+    let denominator = denominator.abs();
+    let (quotient, remainder) = numerator.abs().div_rem(denominator.clone());
+
+    let away_from_zero = match (remainder * 2u32).cmp(&denominator) {
+        Ordering::Greater => true,
+        Ordering::Less => false,
+        Ordering::Equal => match rounding {
+            Rounding::HalfEven => quotient.is_odd(),
+            Rounding::HalfUp => true,
+        },
+    };
+
+    let magnitude = if away_from_zero { quotient + 1u32 } else { quotient };
+
+    return if negative { -magnitude } else { magnitude };
+}
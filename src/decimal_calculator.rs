@@ -0,0 +1,84 @@
+use crate::ast::{ExprCalculator, HIGHEST_ORDER, HIGH_ORDER, LOWEST_ORDER, LOW_ORDER, MEDIUM_ORDER, PrimitiveHandler};
+use crate::decimal::{Decimal, Rounding};
+use crate::literal;
+
+struct DecimalHandler {
+    si_suffixes: bool,
+}
+
+impl PrimitiveHandler<Decimal> for DecimalHandler {
+    fn from_string(&self, input: &String) -> Result<Decimal, ()> {
+        let literal = literal::parse(input, self.si_suffixes).map_err(|_| ())?;
+
+        return Result::Ok(Decimal::from_literal(&literal));
+    }
+
+    fn can_start_with(&self, input: String) -> bool {
+        return literal::can_start_with(&input, self.si_suffixes);
+    }
+
+    fn explain(&self, input: &String) -> Option<String> {
+        return literal::parse(input, self.si_suffixes).err();
+    }
+
+    fn enable_si_suffixes(&mut self) {
+        self.si_suffixes = true;
+    }
+}
+
+/// Addition, subtraction and multiplication are exact, division (and negative powers)
+/// are rounded to `scale` decimal places with the given rounding rule.
+pub fn decimal_calculator(scale: u32, rounding: Rounding) -> ExprCalculator<Decimal> {
+    let mut result = ExprCalculator::<Decimal>::new(Box::new(DecimalHandler { si_suffixes: false }));
+
+    result.add_prefix(
+        "-".to_string(),
+        "Negation".to_string(),
+        Box::new(|op1| { op1.neg() }),
+        HIGH_ORDER,
+    );
+    result.add_infix(
+        "+".to_string(),
+        "Addition".to_string(),
+        Box::new(|op1, op2| { op1.add(&op2) }),
+        LOWEST_ORDER,
+    );
+    result.add_infix(
+        "-".to_string(),
+        "Subtraction".to_string(),
+        Box::new(|op1, op2| { op1.sub(&op2) }),
+        LOWEST_ORDER,
+    );
+    result.add_infix(
+        "*".to_string(),
+        "Multiplication".to_string(),
+        Box::new(|op1, op2| { op1.mul(&op2) }),
+        LOW_ORDER,
+    );
+    result.add_fallible_infix(
+        "/".to_string(),
+        format!("Division, rounded to {} decimal places", scale),
+        Box::new(move |op1, op2| { op1.div(&op2, scale, rounding) }),
+        LOW_ORDER,
+    );
+    result.add_fallible_right_infix(
+        "^".to_string(),
+        "Integer power".to_string(),
+        Box::new(move |op1, op2| { op1.pow(&op2, scale, rounding) }),
+        MEDIUM_ORDER,
+    );
+    result.add_one_argument_function(
+        "abs".to_string(),
+        "Absolute value".to_string(),
+        Box::new(|op1| { op1.abs() }),
+        HIGHEST_ORDER,
+    );
+    result.add_one_argument_function(
+        "round".to_string(),
+        format!("Round to {} decimal places", scale),
+        Box::new(move |op1| { op1.round(scale, rounding) }),
+        HIGHEST_ORDER,
+    );
+
+    return result;
+}
@@ -0,0 +1,77 @@
+use crate::ast::Token;
+
+/// Error of a calculation with the value type erased, so errors of all calculators are reported the same way.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Diagnostic {
+    /// Char index in the whole input
+    pub pos: usize,
+    /// Line of `pos`, starting from 1
+    pub line: usize,
+    /// Char of `pos` within its line, starting from 1
+    pub column: usize,
+    /// Text of the token the error points at
+    pub value: String,
+    /// Reason of the error, most syntax errors only point at the token
+    pub message: Option<String>,
+    /// Whether the expression was parsed and failed while being evaluated
+    pub failure: bool,
+}
+
+impl Diagnostic {
+    /// Syntax error with a reason, on the first line until it is located.
+    pub fn new(pos: usize, value: String, message: String) -> Diagnostic {
+        return Diagnostic { pos, line: 1, column: pos + 1, value, message: Some(message), failure: false };
+    }
+
+    pub fn is_failure(&self) -> bool {
+        return self.failure;
+    }
+
+    pub fn kind(&self) -> &'static str {
+        return if self.is_failure() { "evaluation error" } else { "syntax error" };
+    }
+
+    /// Finds line and column of the error in the `input` it was reported for.
+    pub fn locate(mut self, input: &str) -> Diagnostic {
+        let before: Vec<char> = input.chars().take(self.pos).collect();
+        self.line = before.iter().filter(|it| **it == '\n').count() + 1;
+        self.column = before.iter().rev().take_while(|it| **it != '\n').count() + 1;
+
+        return self;
+    }
+
+    /// Errors after the first line of the input are placed by line and column.
+    pub fn to_string(&self) -> String {
+        let location = if self.line > 1 {
+            format!("line {}, column {}", self.line, self.column)
+        } else {
+            format!("position {}", self.pos)
+        };
+
+        return match &self.message {
+            Some(message) => format!("'{}' at {}: {}", self.value, location, message),
+            None => format!("'{}' at {}", self.value, location),
+        };
+    }
+
+    /// Token and reason without the location, for reports which give it in another form.
+    pub fn describe(&self) -> String {
+        return match &self.message {
+            Some(message) => format!("'{}': {}", self.value, message),
+            None => format!("'{}'", self.value),
+        };
+    }
+}
+
+impl<T: 'static + Clone> From<Token<T>> for Diagnostic {
+    fn from(token: Token<T>) -> Diagnostic {
+        let message = match &token {
+            Token::Failure { message, .. } | Token::Malformed { message, .. } => Some(message.clone()),
+            _ => None,
+        };
+
+        let pos = token.get_pos();
+
+        return Diagnostic { pos, line: 1, column: pos + 1, value: token.get_value(), message, failure: token.is_failure() };
+    }
+}
@@ -0,0 +1,30 @@
+use rug::Float;
+use strum_macros::{Display, EnumString, EnumVariantNames};
+
+/// Number type used by `--float-calc` and the interactive shell.
+#[derive(Clone, Copy, PartialEq, Debug, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Engine {
+    /// Hardware double precision
+    F64,
+    /// rug Float with 64 bit mantissa unless `--precision`
+    Float,
+    /// Exact fractions, no irrational functions
+    Rational,
+}
+
+/// Difference between the precise and the f64 result, and whether it is within `tolerance`.
+/// The tolerance is relative to the magnitude of the result, and absolute for results below 1.
+pub fn compare(precise: &Float, fast: f64, tolerance: f64) -> (f64, bool) {
+    if precise.is_nan() || fast.is_nan() {
+        return (f64::NAN, precise.is_nan() && fast.is_nan());
+    }
+    if precise.is_infinite() || fast.is_infinite() {
+        let same = precise.to_f64() == fast;
+        return (if same { 0.0 } else { f64::INFINITY }, same);
+    }
+
+    let difference = (precise.clone() - fast).abs().to_f64();
+
+    return (difference, difference <= tolerance * fast.abs().max(1.0));
+}
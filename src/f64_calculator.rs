@@ -1,13 +1,19 @@
+use crate::angle::AngleUnit;
 use crate::ast::{ExprCalculator, HIGHEST_ORDER, LOWEST_ORDER, LOW_ORDER, MEDIUM_ORDER, HIGH_ORDER, PrimitiveHandler};
-use regex::Regex;
+use crate::conditions::add_conditions;
+use crate::float_calculator::NUMBER_ALIASES;
+use crate::literal;
 use std::str::FromStr;
 use std::f64::consts::{PI, E};
 
-struct F64Handler {}
+struct F64Handler {
+    si_suffixes: bool,
+}
 
 impl PrimitiveHandler<f64> for F64Handler {
     fn from_string(&self, input: &String) -> Result<f64, ()> {
-        let result = f64::from_str(input);
+        let literal = literal::parse(input, self.si_suffixes).map_err(|_| ())?;
+        let result = f64::from_str(&literal.to_scientific());
         if result.is_err() {
             return Result::Err(())
         }
@@ -16,12 +22,21 @@ impl PrimitiveHandler<f64> for F64Handler {
     }
 
     fn can_start_with(&self, input: String) -> bool {
-        return PRIMITIVE_INCOMPLETE_1.is_match(&input) || PRIMITIVE_INCOMPLETE_2.is_match(&input);
+        return literal::can_start_with(&input, self.si_suffixes);
+    }
+
+    fn explain(&self, input: &String) -> Option<String> {
+        return literal::parse(input, self.si_suffixes).err();
+    }
+
+    fn enable_si_suffixes(&mut self) {
+        self.si_suffixes = true;
     }
 }
 
-pub fn f64_calculator() -> ExprCalculator<f64> {
-    let mut result = ExprCalculator::<f64>::new(Box::new(F64Handler {}));
+/// `angle` is the unit of trigonometric functions.
+pub fn f64_calculator_with(angle: AngleUnit) -> ExprCalculator<f64> {
+    let mut result = ExprCalculator::<f64>::new(Box::new(F64Handler { si_suffixes: false }));
 
     result.add_prefix(
         "-".to_ascii_lowercase(),
@@ -29,6 +44,12 @@ pub fn f64_calculator() -> ExprCalculator<f64> {
         Box::new(|op1| { -op1.clone() }),
         HIGH_ORDER
     );
+    result.add_postfix(
+        "!".to_ascii_lowercase(),
+        "Factorial".to_string(),
+        Box::new(|op1| { (1..=op1.max(0.0).min(171.0) as u32).map(f64::from).product() }),
+        HIGHEST_ORDER
+    );
 
     result.add_infix(
         "+".to_ascii_lowercase(),
@@ -54,54 +75,55 @@ pub fn f64_calculator() -> ExprCalculator<f64> {
         Box::new(|op1, op2| { op1 / op2.clone() }),
         LOW_ORDER,
     );
-    result.add_infix(
+    result.add_right_infix(
         "^".to_ascii_lowercase(),
         "Product".to_string(),
         Box::new(|op1, op2| { op1.powf(op2.clone()) }),
         MEDIUM_ORDER,
     );
-    result.add_postfix(
+    result.add_prefix(
         "sqrt".to_string(),
         "Square root".to_string(),
         Box::new(|op1| { op1.clone().sqrt() }),
         HIGHEST_ORDER
     );
-    result.add_prefix(
+    result.add_one_argument_function(
         "sin".to_string(),
         "Sine".to_string(),
-        Box::new(|op1| { op1.clone().sin() }),
+        Box::new(move |op1| { angle.f64_to_radians(op1).sin() }),
         HIGHEST_ORDER
     );
-    result.add_prefix(
+    result.add_one_argument_function(
         "cos".to_string(),
         "Cosine".to_string(),
-        Box::new(|op1| { op1.clone().cos() }),
+        Box::new(move |op1| { angle.f64_to_radians(op1).cos() }),
         HIGHEST_ORDER
     );
-    result.add_prefix(
+    result.add_one_argument_function(
         "ln".to_string(),
         "Natural logarithm".to_string(),
         Box::new(|op1| { op1.clone().ln() }),
         HIGHEST_ORDER
     );
-    result.add_prefix(
+    result.add_one_argument_function(
         "log10".to_string(),
         "Common logarithm".to_string(),
         Box::new(|op1| { op1.clone().log10() }),
         HIGHEST_ORDER
     );
-    result.add_prefix(
+    result.add_one_argument_function(
         "log2".to_string(),
         "Binary logarithm".to_string(),
         Box::new(|op1| { op1.clone().log2() }),
         HIGHEST_ORDER
     );
-    result.add_prefix(
+    result.add_one_argument_function(
         "exp".to_string(),
         "Exponent".to_string(),
         Box::new(|op1| { op1.clone().exp() }),
         HIGHEST_ORDER
     );
+    add_conditions(&mut result, |value| { *value != 0.0 }, |value| { f64::from(value as u8) });
     result.add_constant(
         "pi".to_string(),
         "Constant Pi=3.1415...".to_string(),
@@ -113,10 +135,9 @@ pub fn f64_calculator() -> ExprCalculator<f64> {
         E,
     );
 
-    return result;
-}
+    for (signature, alias) in NUMBER_ALIASES.iter() {
+        result.add_alias(signature, alias);
+    }
 
-lazy_static! {
-    static ref PRIMITIVE_INCOMPLETE_1: Regex = Regex::new(r"^(\d+|\d+\.\d*)$").unwrap();
-    static ref PRIMITIVE_INCOMPLETE_2: Regex = Regex::new(r"^(\d+|\d+\.\d+)[eE][-+]?\d*$").unwrap();
+    return result;
 }
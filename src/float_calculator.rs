@@ -1,28 +1,48 @@
+use crate::angle::AngleUnit;
 use crate::ast::{ExprCalculator, HIGHEST_ORDER, LOWEST_ORDER, LOW_ORDER, MEDIUM_ORDER, HIGH_ORDER, PrimitiveHandler};
+use crate::conditions::add_conditions;
+use crate::literal;
 use rug::Float;
 use rug::float::Constant;
-use regex::Regex;
 use rug::ops::Pow;
 
-struct FloatHandler {}
+struct FloatHandler {
+    si_suffixes: bool,
+    precision: u32,
+}
 
 impl PrimitiveHandler<Float> for FloatHandler {
     fn from_string(&self, input: &String) -> Result<Float, ()> {
-        let valid = Float::parse(input);
+        let literal = literal::parse(input, self.si_suffixes).map_err(|_| ())?;
+        let valid = Float::parse(literal.to_scientific());
         if valid.is_err() {
             return Result::Err(());
         }
 
-        return Result::Ok(Float::with_val(64, valid.unwrap()));
+        return Result::Ok(Float::with_val(self.precision, valid.unwrap()));
     }
 
     fn can_start_with(&self, input: String) -> bool {
-        return PRIMITIVE_INCOMPLETE_1.is_match(&input) || PRIMITIVE_INCOMPLETE_2.is_match(&input);
+        return literal::can_start_with(&input, self.si_suffixes);
+    }
+
+    fn explain(&self, input: &String) -> Option<String> {
+        return literal::parse(input, self.si_suffixes).err();
+    }
+
+    fn enable_si_suffixes(&mut self) {
+        self.si_suffixes = true;
     }
 }
 
-pub fn float_calculator() -> ExprCalculator<Float> {
-    let mut result = ExprCalculator::<Float>::new(Box::new(FloatHandler {}));
+/// Unicode spellings of the number operations, used by every mode which has the ASCII operation.
+pub const NUMBER_ALIASES: [(&str, &str); 9] = [
+    ("*", "×"), ("*", "·"), ("/", "÷"), ("-", "−"), ("sqrt", "√"), ("pi", "π"), ("<=", "≤"), (">=", "≥"), ("!=", "≠"),
+];
+
+/// Numbers with a `precision` bit mantissa, `angle` is the unit of trigonometric functions.
+pub fn float_calculator_with(precision: u32, angle: AngleUnit) -> ExprCalculator<Float> {
+    let mut result = ExprCalculator::<Float>::new(Box::new(FloatHandler { si_suffixes: false, precision }));
 
     result.add_prefix(
         "-".to_ascii_lowercase(),
@@ -33,7 +53,7 @@ pub fn float_calculator() -> ExprCalculator<Float> {
     result.add_postfix(
         "!".to_ascii_lowercase(),
         "Factorial".to_string(),
-        Box::new(|op1| { Float::with_val(64, Float::factorial((op1 as Float).to_u32_saturating().unwrap())) }),
+        Box::new(move |op1| { Float::with_val(precision, Float::factorial((op1 as Float).to_u32_saturating().unwrap())) }),
         HIGHEST_ORDER
     );
 
@@ -61,7 +81,7 @@ pub fn float_calculator() -> ExprCalculator<Float> {
         Box::new(|op1, op2| { op1 / op2.clone() }),
         LOW_ORDER,
     );
-    result.add_infix(
+    result.add_right_infix(
         "^".to_ascii_lowercase(),
         "Product".to_string(),
         Box::new(|op1, op2| { op1.pow(op2.clone()) }),
@@ -76,13 +96,13 @@ pub fn float_calculator() -> ExprCalculator<Float> {
     result.add_one_argument_function(
         "sin".to_string(),
         "Sine".to_string(),
-        Box::new(|op1| { op1.clone().sin() }),
+        Box::new(move |op1| { angle.to_radians(op1).sin() }),
         HIGHEST_ORDER
     );
     result.add_one_argument_function(
         "cos".to_string(),
         "Cosine".to_string(),
-        Box::new(|op1| { op1.clone().cos() }),
+        Box::new(move |op1| { angle.to_radians(op1).cos() }),
         HIGHEST_ORDER
     );
     result.add_one_argument_function(
@@ -109,21 +129,33 @@ pub fn float_calculator() -> ExprCalculator<Float> {
         Box::new(|op1| { op1.clone().exp() }),
         HIGHEST_ORDER
     );
+    add_conditions(&mut result, |value| { !value.is_zero() }, move |value| { Float::with_val(precision, value as u32) });
+    result.add_postfix(
+        "²".to_string(),
+        "Square".to_string(),
+        Box::new(|op1| { op1.clone() * op1 }),
+        HIGHEST_ORDER
+    );
+    result.add_postfix(
+        "³".to_string(),
+        "Cube".to_string(),
+        Box::new(|op1| { op1.clone() * op1.clone() * op1 }),
+        HIGHEST_ORDER
+    );
     result.add_constant(
         "pi".to_string(),
         "Constant Pi=3.1415...".to_string(),
-        Float::with_val(64, Constant::Pi),
+        Float::with_val(precision, Constant::Pi),
     );
     result.add_constant(
         "e".to_string(),
         "Constant e=2.7182....".to_string(),
-        Float::with_val(64, 1).exp(),
+        Float::with_val(precision, 1).exp(),
     );
 
-    return result;
-}
+    for (signature, alias) in NUMBER_ALIASES.iter() {
+        result.add_alias(signature, alias);
+    }
 
-lazy_static! {
-    static ref PRIMITIVE_INCOMPLETE_1: Regex = Regex::new(r"^(\d+|\d+\.\d*)$").unwrap();
-    static ref PRIMITIVE_INCOMPLETE_2: Regex = Regex::new(r"^(\d+|\d+\.\d+)[eE][-+]?\d*$").unwrap();
+    return result;
 }
@@ -0,0 +1,460 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use regex::Regex;
+use rug::{Float, Rational};
+use strum::VariantNames;
+use strum_macros::{Display, EnumString, EnumVariantNames};
+
+/// Notation of numeric results.
+#[derive(Clone, Copy, PartialEq, Debug, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Notation {
+    /// Fixed point for moderate magnitudes, scientific below 1e-6 and from 1e21
+    Auto,
+    /// One digit before the point: `1.2345e3`
+    Sci,
+    /// Exponent is a multiple of three: `12.345e3`
+    Eng,
+    /// No exponent: `12345`
+    Fixed,
+}
+
+/// How numeric results are printed.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct NumberFormat {
+    pub notation: Notation,
+    /// Significant digits, all digits the value type guarantees when not set
+    pub digits: Option<usize>,
+    /// Digits after the decimal point. Trailing zeros are kept, otherwise they are trimmed
+    pub decimals: Option<usize>,
+    /// `,` between every three digits of the integer part
+    pub thousands: bool,
+    /// Radix of the digits, 2 to 36
+    pub base: u32,
+    /// Add the IEEE-754 bit pattern of f64 results
+    pub ieee: bool,
+}
+
+impl Default for NumberFormat {
+    fn default() -> NumberFormat {
+        return NumberFormat { notation: Notation::Auto, digits: None, decimals: None, thousands: false, base: 10, ieee: false };
+    }
+}
+
+impl NumberFormat {
+    /// Nothing was asked for: exact types such as fractions keep their own notation.
+    pub fn is_default(&self) -> bool {
+        return *self == NumberFormat::default();
+    }
+
+    /// Changes the format by words such as `sci`, `digits 4`, `decimals all`, `thousands on`, `hex` or `base 36`.
+    pub fn apply(&self, command: &str) -> Result<NumberFormat, String> {
+        let mut result = *self;
+        let mut words = command.split_whitespace();
+        while let Some(word) = words.next() {
+            match word {
+                "digits" => result.digits = count(word, words.next())?,
+                "decimals" => result.decimals = count(word, words.next())?,
+                "thousands" => result.thousands = switch(word, words.next())?,
+                "ieee" => result.ieee = switch(word, words.next())?,
+                "hex" => result.base = 16,
+                "oct" => result.base = 8,
+                "bin" => result.base = 2,
+                "dec" => result.base = 10,
+                "base" => result.base = base(words.next())?,
+                it => result.notation = Notation::from_str(it).map_err(|_| {
+                    format!(
+                        "unknown format '{}', expected one of: {}, digits, decimals, thousands, hex, oct, bin, dec, base, ieee",
+                        it,
+                        Notation::VARIANTS.join(", ")
+                    )
+                })?,
+            }
+        }
+
+        return Result::Ok(result);
+    }
+
+    /// Splits off a trailing conversion, `255 to hex` or `1/3 to sci digits 3`, and applies it to the format.
+    /// On error, also when results of type `T` can't be converted so, gives the char position of the conversion
+    /// and the reason.
+    pub fn split_conversion<'a, T: Format>(&self, input: &'a str) -> Result<(&'a str, NumberFormat), (usize, String)> {
+        let captures = match CONVERSION.captures(input) {
+            Some(it) => it,
+            None => return Result::Ok((input, *self)),
+        };
+        let target = captures.get(2).unwrap();
+        let error = |message| (input[..target.start()].chars().count(), message);
+        let format = self.apply(target.as_str()).map_err(error)?;
+        // only the words of the conversion, the settings were accepted already
+        T::converts(&NumberFormat::default().apply(target.as_str()).unwrap()).map_err(error)?;
+
+        return Result::Ok((captures.get(1).unwrap().as_str(), format));
+    }
+
+    pub fn to_string(&self) -> String {
+        let optional = |value: Option<usize>| { value.map_or("all".to_string(), |it| it.to_string()) };
+
+        let on_off = |value: bool| { if value { "on" } else { "off" } };
+
+        return format!(
+            "{}, digits {}, decimals {}, thousands {}, base {}, ieee {}",
+            self.notation,
+            optional(self.digits),
+            optional(self.decimals),
+            on_off(self.thousands),
+            self.base,
+            on_off(self.ieee)
+        );
+    }
+}
+
+/// Value type whose results can be printed with a `NumberFormat`.
+pub trait Format {
+    fn format(&self, format: &NumberFormat) -> String;
+    /// Name of the type of a result, as given by `--output json`.
+    fn type_name(&self) -> &'static str;
+    /// All digits of the value, written so that a statement reads the same value back.
+    /// Infinities and NaN are written as the divisions giving them, `1/0` and `0/0`.
+    fn to_input(&self) -> String;
+    /// Whether results can be converted to `format`, as asked for by `to hex`, otherwise the reason.
+    fn converts(format: &NumberFormat) -> Result<(), String> where Self: Sized {
+        if format.ieee {
+            return Result::Err("'ieee' only applies to results of the f64 mode".to_string());
+        }
+
+        return Result::Ok(());
+    }
+}
+
+/// Finite number as decimal digits: `0.DIGITS * 10^exponent`, so `-21.5` is `-0.215e2`.
+#[derive(Clone)]
+pub struct Digits {
+    pub negative: bool,
+    pub digits: String,
+    pub exponent: i32,
+}
+
+impl Digits {
+    pub fn format(&self, format: &NumberFormat) -> String {
+        let mut number = if self.digits.chars().all(|it| it == '0') {
+            Digits { negative: false, digits: "0".to_string(), exponent: 1 }
+        } else {
+            Digits { negative: self.negative, digits: self.digits.trim_start_matches('0').to_string(), exponent: self.exponent - leading_zeros(&self.digits) }
+        };
+        if let Some(digits) = format.digits {
+            number = number.round(digits.max(1) as i32);
+        }
+
+        let notation = match format.notation {
+            Notation::Auto if number.exponent >= -5 && number.exponent <= 21 => Notation::Fixed,
+            Notation::Auto => Notation::Sci,
+            it => it,
+        };
+        let power = match notation {
+            Notation::Sci => number.exponent - 1,
+            Notation::Eng => (number.exponent - 1).div_euclid(3) * 3,
+            _ => 0,
+        };
+        if let Some(decimals) = format.decimals {
+            number = number.round(number.exponent - power + decimals as i32);
+        }
+        // rounding may carry into a new digit, 9.99 -> 10.0, which moves the point in sci and eng notation
+        let power = match notation {
+            Notation::Sci => number.exponent - 1,
+            Notation::Eng => (number.exponent - 1).div_euclid(3) * 3,
+            _ => 0,
+        };
+
+        let mantissa = number.layout(number.exponent - power, format);
+        if power == 0 && notation == Notation::Fixed {
+            return mantissa;
+        }
+        return format!("{}e{}", mantissa, power);
+    }
+
+    /// Keeps `count` significant digits, rounding half up.
+    fn round(&self, count: i32) -> Digits {
+        if count >= self.digits.len() as i32 {
+            return self.clone();
+        }
+        if count < 0 || (count == 0 && self.digits.as_bytes()[0] < b'5') {
+            return Digits { negative: false, digits: "0".to_string(), exponent: 1 };
+        }
+
+        let mut kept: Vec<u8> = self.digits.as_bytes()[..count as usize].to_vec();
+        let mut exponent = self.exponent;
+        if self.digits.as_bytes()[count as usize] >= b'5' {
+            let mut index = kept.len();
+            loop {
+                if index == 0 {
+                    kept.insert(0, b'1');
+                    kept.truncate(count.max(1) as usize);
+                    exponent += 1;
+                    break;
+                }
+                index -= 1;
+                if kept[index] == b'9' {
+                    kept[index] = b'0';
+                } else {
+                    kept[index] += 1;
+                    break;
+                }
+            }
+        }
+
+        return Digits { negative: self.negative, digits: String::from_utf8(kept).unwrap(), exponent };
+    }
+
+    /// Digits with the decimal point after `point` of them.
+    fn layout(&self, point: i32, format: &NumberFormat) -> String {
+        let padded = if point <= 0 {
+            format!("{}{}", "0".repeat((1 - point) as usize), self.digits)
+        } else if point as usize > self.digits.len() {
+            format!("{}{}", self.digits, "0".repeat(point as usize - self.digits.len()))
+        } else {
+            self.digits.clone()
+        };
+        let split = point.max(1) as usize;
+        let integer = &padded[..split];
+        let mut fraction = padded[split..].to_string();
+
+        match format.decimals {
+            Some(decimals) => fraction = format!("{:0<1$}", fraction, decimals),
+            None => fraction = fraction.trim_end_matches('0').to_string(),
+        }
+
+        let sign = if self.negative { "-" } else { "" };
+        let integer = if format.thousands { group_thousands(integer) } else { integer.to_string() };
+        if fraction.is_empty() {
+            return format!("{}{}", sign, integer);
+        }
+        return format!("{}{}.{}", sign, integer, fraction);
+    }
+}
+
+/// `1234567` -> `1,234,567`
+pub fn group_thousands(integer: &str) -> String {
+    let mut grouped = String::new();
+    for (index, digit) in integer.chars().enumerate() {
+        if index > 0 && (integer.len() - index) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+
+    return grouped;
+}
+
+fn switch(name: &str, word: Option<&str>) -> Result<bool, String> {
+    return match word {
+        Some("on") => Result::Ok(true),
+        Some("off") => Result::Ok(false),
+        _ => Result::Err(format!("expected 'on' or 'off' after '{}'", name)),
+    };
+}
+
+fn base(word: Option<&str>) -> Result<u32, String> {
+    return match word.map(|it| it.parse::<u32>()) {
+        Some(Ok(base)) if (2..=36).contains(&base) => Result::Ok(base),
+        _ => Result::Err("expected a base from 2 to 36 after 'base'".to_string()),
+    };
+}
+
+/// `value` in base `radix`: `0xff`, `0o17` and `0b101` can be read back, other bases are written as `36#zz`.
+/// Fraction digits are cut after `limit`, which is marked by `...`.
+pub fn to_radix(value: &Rational, radix: u32, limit: usize) -> String {
+    let negative = value.cmp0() == Ordering::Less;
+    let (integer, mut remainder) = value.numer().clone().abs().div_rem(value.denom().clone());
+
+    let mut fraction = String::new();
+    while remainder.cmp0() != Ordering::Equal && fraction.len() < limit {
+        let (digit, rest) = (remainder * radix).div_rem(value.denom().clone());
+        fraction.push(std::char::from_digit(digit.to_u32().unwrap(), radix).unwrap());
+        remainder = rest;
+    }
+    if remainder.cmp0() != Ordering::Equal {
+        fraction.push_str("...");
+    }
+
+    let prefix = match radix {
+        16 => "0x".to_string(),
+        8 => "0o".to_string(),
+        2 => "0b".to_string(),
+        it => format!("{}#", it),
+    };
+    let sign = if negative { "-" } else { "" };
+    if fraction.is_empty() {
+        return format!("{}{}{}", sign, prefix, integer.to_string_radix(radix as i32));
+    }
+    return format!("{}{}{}.{}", sign, prefix, integer.to_string_radix(radix as i32), fraction);
+}
+
+/// Fraction digits in base `radix` which `bits` binary digits can tell apart.
+fn radix_limit(bits: u32, radix: u32, format: &NumberFormat) -> usize {
+    return format.digits.unwrap_or_else(|| (bits as f64 / (radix as f64).log2()).ceil() as usize);
+}
+
+/// Sign, biased exponent and mantissa fields of an f64.
+fn ieee_view(value: f64) -> String {
+    let bits = value.to_bits();
+    let exponent = (bits >> 52) & 0x7ff;
+    let meaning = match exponent {
+        0 => "subnormal".to_string(),
+        0x7ff => "infinity or NaN".to_string(),
+        it => format!("2^{}", it as i64 - 1023),
+    };
+
+    return format!(
+        "bits 0x{:016X}\nsign {}, exponent {:011b} ({}), mantissa {:052b}",
+        bits,
+        bits >> 63,
+        exponent,
+        meaning,
+        bits & ((1 << 52) - 1)
+    );
+}
+
+fn count(name: &str, word: Option<&str>) -> Result<Option<usize>, String> {
+    return match word {
+        Some("all") => Result::Ok(None),
+        Some(it) => it.parse::<usize>().map(Some).map_err(|_| format!("expected a number or 'all' after '{}', got '{}'", name, it)),
+        None => Result::Err(format!("expected a number or 'all' after '{}'", name)),
+    };
+}
+
+fn leading_zeros(digits: &str) -> i32 {
+    return (digits.len() - digits.trim_start_matches('0').len()) as i32;
+}
+
+impl Format for f64 {
+    /// Without `--digits` the shortest digits which read back as the same f64 are printed.
+    fn format(&self, format: &NumberFormat) -> String {
+        if format.ieee {
+            let plain = NumberFormat { ieee: false, ..*format };
+            return format!("{}\n{}", self.format(&plain), ieee_view(*self));
+        }
+        if !self.is_finite() {
+            return self.to_string();
+        }
+        if format.base != 10 {
+            return to_radix(&Rational::from_f64(*self).unwrap(), format.base, radix_limit(53, format.base, format));
+        }
+
+        let scientific = format!("{:e}", self.abs());
+        let mut parts = scientific.splitn(2, 'e');
+        let mantissa = parts.next().unwrap().replace('.', "");
+        let exponent = parts.next().unwrap().parse::<i32>().unwrap();
+
+        return Digits { negative: self.is_sign_negative(), digits: mantissa, exponent: exponent + 1 }.format(format);
+    }
+
+    fn type_name(&self) -> &'static str {
+        return "number";
+    }
+
+    fn to_input(&self) -> String {
+        if !self.is_finite() {
+            return non_finite_input(self.is_nan(), self.is_sign_negative());
+        }
+
+        return format!("{:e}", self);
+    }
+
+    fn converts(_format: &NumberFormat) -> Result<(), String> {
+        return Result::Ok(());
+    }
+}
+
+impl Format for Float {
+    /// Without `--digits` only the digits the precision guarantees are printed, 19 for a 64 bit mantissa.
+    fn format(&self, format: &NumberFormat) -> String {
+        if format.base != 10 {
+            return match self.to_rational() {
+                Some(exact) => to_radix(&exact, format.base, radix_limit(self.prec(), format.base, format)),
+                None => self.to_string(),
+            };
+        }
+
+        let guaranteed = (self.prec() as f64 * std::f64::consts::LOG10_2).floor() as usize;
+        let (negative, digits, exponent) = self.to_sign_string_exp(10, Some(guaranteed));
+
+        return match exponent {
+            Some(exponent) => Digits { negative, digits, exponent }.format(format),
+            None if self.is_zero() => Digits { negative: false, digits: "0".to_string(), exponent: 1 }.format(format),
+            None => self.to_string(),
+        };
+    }
+
+    fn type_name(&self) -> &'static str {
+        return "number";
+    }
+
+    fn to_input(&self) -> String {
+        if !self.is_finite() {
+            return non_finite_input(self.is_nan(), self.is_sign_negative());
+        }
+
+        let (negative, digits, exponent) = self.to_sign_string_exp(10, None);
+        let sign = if negative { "-" } else { "" };
+
+        return match exponent {
+            Some(exponent) => format!("{}0.{}e{}", sign, digits, exponent),
+            None => format!("{}0", sign),
+        };
+    }
+}
+
+impl Format for Rational {
+    /// Fractions stay fractions unless a format is asked for.
+    fn format(&self, format: &NumberFormat) -> String {
+        if format.is_default() {
+            return self.to_string();
+        }
+        if format.base != 10 {
+            return to_radix(self, format.base, radix_limit(64, format.base, format));
+        }
+
+        return Float::with_val(64, self).format(format);
+    }
+
+    fn type_name(&self) -> &'static str {
+        return "rational";
+    }
+
+    fn to_input(&self) -> String {
+        return self.to_string();
+    }
+}
+
+impl Format for bool {
+    fn format(&self, _format: &NumberFormat) -> String {
+        return self.to_string();
+    }
+
+    fn type_name(&self) -> &'static str {
+        return "boolean";
+    }
+
+    fn to_input(&self) -> String {
+        return self.to_string();
+    }
+
+    fn converts(_format: &NumberFormat) -> Result<(), String> {
+        return Result::Err("boolean results can't be converted, 'to' only applies to numbers".to_string());
+    }
+}
+
+fn non_finite_input(nan: bool, negative: bool) -> String {
+    return match (nan, negative) {
+        (true, _) => "0/0",
+        (false, false) => "1/0",
+        (false, true) => "-1/0",
+    }.to_string();
+}
+
+lazy_static! {
+    /// A statement continued with `\` may also be continued before `to`
+    static ref CONVERSION: Regex = Regex::new(r"(?s)^(.*?)(?:\s|\\\r?\n)+to\s+(\S.*?)\s*$").unwrap();
+}
@@ -0,0 +1,138 @@
+use std::convert::TryFrom;
+
+use regex::Regex;
+use rug::Integer;
+
+/// Number literal as written in an expression, with digit separators removed.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Literal {
+    /// `digits * 10^exponent`: `4.7k` has digits `47` and exponent `2`
+    Decimal { digits: String, exponent: i32 },
+    /// Integer written with a `0x`, `0o` or `0b` prefix
+    Radix { radix: u32, digits: String },
+}
+
+impl Literal {
+    /// Value as `mantissa * 10^exponent`, so exact types don't lose anything.
+    pub fn to_mantissa_and_exponent(&self) -> (Integer, i32) {
+        return match self {
+            Literal::Decimal { digits, exponent } => (Integer::from_str_radix(digits, 10).unwrap(), *exponent),
+            Literal::Radix { radix, digits } => (Integer::from_str_radix(digits, *radix as i32).unwrap(), 0),
+        };
+    }
+
+    /// Scientific notation understood by `f64::from_str` and `Float::parse`.
+    pub fn to_scientific(&self) -> String {
+        let (mantissa, exponent) = self.to_mantissa_and_exponent();
+
+        return format!("{}e{}", mantissa, exponent);
+    }
+}
+
+/// Largest magnitude of the exponent of a decimal literal. Exact types build `10^exponent`,
+/// which takes too long beyond it.
+pub const MAX_EXPONENT: i32 = 100_000;
+
+/// Multipliers of the SI suffixes, `u` and `µ` both stand for micro.
+const SI_SUFFIXES: [(char, i32); 11] = [
+    ('f', -15), ('p', -12), ('n', -9), ('u', -6), ('µ', -6), ('m', -3),
+    ('k', 3), ('M', 6), ('G', 9), ('T', 12), ('P', 15),
+];
+
+/// Whether `input` may grow into a literal. Runs of digits, dots and underscores are always
+/// accepted, so `1.2.3` becomes one token and is reported as malformed instead of as two numbers.
+pub fn can_start_with(input: &str, si_suffixes: bool) -> bool {
+    if RADIX_INCOMPLETE.is_match(input) || DECIMAL_INCOMPLETE.is_match(input) {
+        return true;
+    }
+
+    let last = match input.chars().last() {
+        Some(it) => it,
+        None => return false,
+    };
+
+    return si_suffixes && suffix_exponent(last).is_some() && DECIMAL_COMPLETE.is_match(&input[..input.len() - last.len_utf8()]);
+}
+
+/// Reads a whole literal, the error tells what is wrong with it.
+pub fn parse(input: &str, si_suffixes: bool) -> Result<Literal, String> {
+    let lower = input.to_ascii_lowercase();
+    for (prefix, radix, name) in [("0x", 16, "hexadecimal number"), ("0o", 8, "octal number"), ("0b", 2, "binary number")].iter() {
+        if lower.starts_with(prefix) {
+            let digits = separated_digits(&input[2..], *radix, name)?;
+            if digits.is_empty() {
+                return Result::Err(format!("missing digits after '{}'", &input[..2]));
+            }
+            return Result::Ok(Literal::Radix { radix: *radix, digits });
+        }
+    }
+
+    let mut number = input;
+    let mut exponent = 0;
+    if si_suffixes {
+        let last = input.chars().last().unwrap_or(' ');
+        if let Some(shift) = suffix_exponent(last) {
+            number = &input[..input.len() - last.len_utf8()];
+            exponent = shift;
+        }
+    }
+
+    let mut parts = number.splitn(2, |it| it == 'e' || it == 'E');
+    let mantissa = parts.next().unwrap();
+    if let Some(power) = parts.next() {
+        let unsigned = power.trim_start_matches(|it| it == '+' || it == '-');
+        if power.len() - unsigned.len() > 1 {
+            return Result::Err("more than one sign in the exponent".to_string());
+        }
+        let digits = separated_digits(unsigned, 10, "exponent")?;
+        if digits.is_empty() {
+            return Result::Err("missing exponent digits".to_string());
+        }
+        let value = digits.parse::<i32>().map_err(|_| "exponent is too large".to_string())?;
+        exponent = exponent.checked_add(if power.starts_with('-') { -value } else { value }).ok_or("exponent is too large".to_string())?;
+    }
+
+    if mantissa.matches('.').count() > 1 {
+        return Result::Err("more than one decimal point".to_string());
+    }
+    let mut parts = mantissa.splitn(2, '.');
+    let whole = separated_digits(parts.next().unwrap(), 10, "number")?;
+    let fraction = separated_digits(parts.next().unwrap_or(""), 10, "number")?;
+    if whole.is_empty() && fraction.is_empty() {
+        return Result::Err("missing digits".to_string());
+    }
+
+    let exponent = i32::try_from(fraction.len()).ok()
+        .and_then(|it| exponent.checked_sub(it))
+        .filter(|it| (-MAX_EXPONENT..=MAX_EXPONENT).contains(it))
+        .ok_or("exponent is too large".to_string())?;
+
+    return Result::Ok(Literal::Decimal { digits: format!("{}{}", whole, fraction), exponent });
+}
+
+fn suffix_exponent(suffix: char) -> Option<i32> {
+    return SI_SUFFIXES.iter().find(|(it, _)| *it == suffix).map(|(_, exponent)| *exponent);
+}
+
+/// Digits of `input` without separators. An underscore is only allowed between two digits.
+fn separated_digits(input: &str, radix: u32, name: &str) -> Result<String, String> {
+    if input.starts_with('_') || input.ends_with('_') || input.contains("__") {
+        return Result::Err("digit separator '_' must be placed between digits".to_string());
+    }
+
+    let mut digits = String::new();
+    for char in input.chars().filter(|it| *it != '_') {
+        if !char.is_digit(radix) {
+            return Result::Err(format!("invalid digit '{}' in {}", char, name));
+        }
+        digits.push(char);
+    }
+
+    return Result::Ok(digits);
+}
+
+lazy_static! {
+    static ref RADIX_INCOMPLETE: Regex = Regex::new(r"^0([xXoObB][0-9a-zA-Z_]*)?$").unwrap();
+    static ref DECIMAL_INCOMPLETE: Regex = Regex::new(r"^[0-9.][0-9._]*([eE][-+]*[0-9._]*)?$").unwrap();
+    static ref DECIMAL_COMPLETE: Regex = Regex::new(r"^[0-9.][0-9._]*([eE][-+]?[0-9_]+)?$").unwrap();
+}
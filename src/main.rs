@@ -2,13 +2,24 @@
 extern crate lazy_static;
 extern crate rug;
 
-use std::fmt::Display;
-use std::io::{stdout, Write};
 use std::process::exit;
+use std::str::FromStr;
 
+use atty::Stream;
 use clap::{AppSettings, Clap};
+use strum::VariantNames;
+use unicode_width::UnicodeWidthChar;
 
-use crate::ast::{Token, ExprCalculator};
+use crate::angle::AngleUnit;
+use crate::ast::Token;
+use crate::decimal::Rounding;
+use crate::diagnostic::Diagnostic;
+use crate::engine::Engine;
+use crate::format::{NumberFormat, Notation};
+use crate::output::{json_error, json_outcome, json_usage_error, Output};
+use crate::registry::{AnyCalculator, Mode, Outcome, Settings};
+use crate::shell::Shell;
+use crate::truth_table::TruthTable;
 
 mod operation_executor;
 mod ast;
@@ -16,92 +27,931 @@ mod bool_calculator;
 mod float_calculator;
 mod tests;
 mod f64_calculator;
+mod decimal;
+mod decimal_calculator;
+mod tensor;
+mod matrix_calculator;
+mod truth_table;
+mod boolean_forms;
+mod value;
+mod mixed_calculator;
+mod conditions;
+mod rational_calculator;
+mod engine;
+mod diagnostic;
+mod registry;
+mod literal;
+mod format;
+mod script;
+mod output;
+mod shell;
+mod angle;
+
+/// Exit status of a wrong command line, the same as for the errors reported by clap.
+const USAGE_ERROR: i32 = 1;
+/// Exit status of an expression which can't be parsed.
+const SYNTAX_ERROR: i32 = 2;
+/// Exit status of an expression which failed while being calculated, e.g. division by zero or a domain error.
+const EVALUATION_ERROR: i32 = 3;
+
+/// Mantissa bits accepted by `--precision` and `:set precision`.
+const PRECISIONS: std::ops::RangeInclusive<u32> = 2..=65536;
+
+/// First line of a script written by `:save`, followed by the name of the mode.
+const SESSION_HEADER: &str = "# expr_calc session, mode ";
+
+/// Commands of the interactive shell and what they do, printed by `:help`.
+const SHELL_COMMANDS: [(&str, &str); 12] = [
+    ("exit", "Leave the shell"),
+    ("list", "Supported operations of the mode"),
+    ("history [clear]", "Numbered results, 'clear' forgets them"),
+    (":help [name]", "This list, usage of a command or syntax, description and priority of an operation"),
+    (":set [setting value]", "Print the settings or change one: mode, precision, scale, rounding, angle or format"),
+    (":mode <name>", "Switch the calculator, ':help set' lists the modes"),
+    (":engine <name>", "Switch the number type: f64, float or rational"),
+    (":format <options>", "Change how results are printed, e.g. ':format sci digits 4', ':format decimals all'"),
+    (":base <2-36>", "Print results in another radix, or end an expression with e.g. 'to hex'"),
+    (":save <file>", "Write the variables to a script, which ':load' and --file read"),
+    (":load <file>", "Run the statements of a script, in the mode it was saved in"),
+    (":reset", "Forget variables, results and history, the settings are kept"),
+];
 
 #[derive(Clap)]
 #[clap(version = "0.1", author = "Andrey G. <rjhdbylive@gmail.com>")]
 #[clap(setting = AppSettings::ColoredHelp)]
 struct Opts {
+    #[clap(subcommand)]
+    command: Option<Command>,
+    #[clap(about = "Expressions to calculate in --mode, the same as 'eval'. Put '--' before an expression starting with '-'", value_name = "expr")]
+    expressions: Vec<String>,
+    #[clap(short, long, about = "Calculate expression in --mode. \nE.g \"-10+sin(23)/(2e10 -1.3)\"", value_name = "expr")]
+    calculate: Option<String>,
+    #[clap(long, about = "Calculator used by eval, check, repl, list, --calculate and --interactive, see --modes", value_name = "mode", global = true)]
+    mode: Option<String>,
+    #[clap(long, about = "List available modes")]
+    modes: bool,
     #[clap(short, long, about = "Calculate expression. \nE.g \"-10+sin(23)/(2e10 -1.3)\"", value_name = "expr")]
     float_calc: Option<String>,
+    #[clap(short, long, about = "Number type used by --float-calc, and instead of --mode: f64, float or rational", default_value = "float", value_name = "engine", global = true)]
+    engine: Engine,
+    #[clap(long, about = "Calculate expression with f64 and float engines and compare the results", value_name = "expr")]
+    compare: Option<String>,
+    #[clap(long, about = "Largest difference accepted by --compare, relative to the result", default_value = "1e-12", value_name = "tolerance")]
+    tolerance: f64,
     #[clap(short, long, about = "Calculate boolean expression. \nE.g \"true | false ^ (true&!false)\"", value_name = "expr")]
     bool_calc: Option<String>,
+    #[clap(long, about = "Calculate expression mixing numbers, comparisons and booleans. \nE.g \"if(2^10 > 1000, min(3, 4), 0) == 3 & !false\"", value_name = "expr")]
+    mixed_calc: Option<String>,
+    #[clap(short, long, about = "Print truth table of boolean expression with variables. \nE.g \"a & !b | c\"", value_name = "expr")]
+    truth_table: Option<String>,
+    #[clap(long, about = "Print --truth-table as CSV")]
+    csv: bool,
+    #[clap(long, about = "Convert boolean expression to conjunctive normal form", value_name = "expr")]
+    cnf: Option<String>,
+    #[clap(long, about = "Convert boolean expression to disjunctive normal form", value_name = "expr")]
+    dnf: Option<String>,
+    #[clap(long, about = "Minimize boolean expression (Quine-McCluskey)", value_name = "expr")]
+    minimize: Option<String>,
+    #[clap(long, about = "Check whether boolean expression is satisfiable", value_name = "expr")]
+    satisfiable: Option<String>,
+    #[clap(long, about = "Check whether boolean expression is a tautology", value_name = "expr")]
+    tautology: Option<String>,
+    #[clap(long, about = "Check whether two boolean expressions are equivalent", number_of_values = 2, value_names = &["expr1", "expr2"])]
+    equivalent: Vec<String>,
+    #[clap(short, long, about = "Calculate expression with exact decimal arithmetic. \nE.g \"19.99*3 - 0.1/3\"", value_name = "expr")]
+    decimal_calc: Option<String>,
+    #[clap(short, long, about = "Calculate vector and matrix expression. \nE.g \"inv([[1,2],[3,4]]) * [1, 1]\"", value_name = "expr")]
+    matrix_calc: Option<String>,
+    #[clap(long, about = "Mantissa bits of the float and mixed modes, 2 to 65536", default_value = "64", value_name = "bits", global = true)]
+    precision: u32,
+    #[clap(long, about = "Unit of the arguments of sin and cos: rad or deg", default_value = "rad", value_name = "unit", global = true)]
+    angle: AngleUnit,
+    #[clap(long, about = "Decimal places kept by the decimal mode", default_value = "2", value_name = "digits", global = true)]
+    scale: u32,
+    #[clap(long, about = "Rounding used by the decimal mode: half-even (banker's) or half-up", default_value = "half-even", value_name = "rule", global = true)]
+    rounding: Rounding,
+    #[clap(long, about = "Print decimal mode results with thousands separators and two decimals", global = true)]
+    currency: bool,
+    #[clap(long, about = "Multiply adjacent operands: 2pi, 3(x+1), (a)(b)", global = true)]
+    implicit_mul: bool,
+    #[clap(long, about = "Read SI suffixes on numbers: 4.7k, 10M, 3u", global = true)]
+    si_suffixes: bool,
+    #[clap(long, about = "Notation of results: auto, sci, eng or fixed", default_value = "auto", value_name = "notation", global = true)]
+    format: Notation,
+    #[clap(long, about = "Significant digits of results", value_name = "count", global = true)]
+    digits: Option<usize>,
+    #[clap(long, about = "Digits after the decimal point of results, trailing zeros are kept", value_name = "count", global = true)]
+    decimals: Option<usize>,
+    #[clap(long, about = "Print results with thousands separators", global = true)]
+    thousands: bool,
+    #[clap(long, about = "Radix of results, 2 to 36. A single result can be converted with a suffix: \"255 to hex\"", default_value = "10", value_name = "base", global = true)]
+    base: u32,
+    #[clap(long, about = "Print the IEEE-754 bit pattern of f64 results", global = true)]
+    ieee: bool,
     #[clap(short, long, about = "Start interactive shell")]
     interactive: bool,
-    #[clap(short, long, about = "Supported operations list")]
+    #[clap(long, about = "Calculate one statement per line of stdin, also used when stdin is not a terminal")]
+    batch: bool,
+    #[clap(long, about = "Print each --batch input line before its result, separated by a tab", global = true)]
+    echo: bool,
+    #[clap(short, long, about = "Supported operations list, of all modes unless --mode is given")]
     list: bool,
+    #[clap(long, about = "Run a script of assignments and expressions in --mode, separated by newlines or ';', '#' starts a comment", value_name = "path", global = true)]
+    file: Option<String>,
+    #[clap(long, about = "Print only the last result of --file", global = true)]
+    quiet: bool,
+    #[clap(long, about = "Continue --file after a failed statement", global = true)]
+    keep_going: bool,
+    #[clap(long, about = "Print results and errors of calculations, --batch and --file as text or json, one object per line", default_value = "text", value_name = "format", global = true)]
+    output: Output,
+    #[clap(long, about = "Only validate the expression, --batch input or --file: print errors, no results", global = true)]
+    check: bool,
+}
 
+#[derive(Clap)]
+enum Command {
+    #[clap(about = "Calculate expressions in --mode, one result per line, or the lines of stdin when none are given")]
+    Eval(Expressions),
+    #[clap(about = "Validate expressions like eval, print errors but no results")]
+    Check(Expressions),
+    #[clap(about = "Start interactive shell")]
+    Repl,
+    #[clap(about = "Supported operations list, of all modes unless --mode is given")]
+    List,
+}
+
+#[derive(Clap)]
+struct Expressions {
+    #[clap(about = "Statements run one after another, 'x = 2' assigns a variable for the following ones", value_name = "expr")]
+    expressions: Vec<String>,
+}
+
+impl Opts {
+    fn mode_name(&self) -> String {
+        return self.mode.clone().unwrap_or(self.engine.to_string());
+    }
+
+    fn settings(&self) -> Settings {
+        return Settings {
+            precision: self.precision,
+            angle: self.angle,
+            scale: self.scale,
+            rounding: self.rounding,
+            currency: self.currency,
+            implicit_multiplication: self.implicit_mul,
+            si_suffixes: self.si_suffixes,
+            format: NumberFormat {
+                notation: self.format,
+                digits: self.digits,
+                decimals: self.decimals,
+                thousands: self.thousands,
+                base: self.base,
+                ieee: self.ieee,
+            },
+        };
+    }
 }
 
 fn main() {
     let opts: Opts = Opts::parse();
+    let settings = opts.settings();
+    if !(2..=36).contains(&opts.base) {
+        usage_error("--base must be from 2 to 36", opts.output)
+    }
+    if !PRECISIONS.contains(&opts.precision) {
+        usage_error("--precision must be from 2 to 65536", opts.output)
+    }
+
+    match &opts.command {
+        Some(Command::Eval(it)) => evaluate(&it.expressions, &opts, &settings, opts.check),
+        Some(Command::Check(it)) => evaluate(&it.expressions, &opts, &settings, true),
+        Some(Command::Repl) if !atty::is(Stream::Stdin) => {
+            run_statements(stdin_statements(), &opts.mode_name(), &settings, opts.echo, opts.output, opts.check)
+        }
+        Some(Command::Repl) => run_repl(&opts.mode_name(), settings),
+        Some(Command::List) => print_operators(opts.mode.as_deref(), &settings),
+        None if !opts.expressions.is_empty() => evaluate(&opts.expressions, &opts, &settings, opts.check),
+        None => run_options(&opts, settings),
+    }
+}
+
+/// `eval` and `check`: the given expressions, else `--file`, else the lines of stdin.
+fn evaluate(expressions: &[String], opts: &Opts, settings: &Settings, check: bool) {
+    if opts.file.is_some() {
+        run_script(opts.file.as_ref().unwrap(), &opts.mode_name(), settings, opts.quiet, opts.keep_going, opts.output, check)
+    } else if expressions.is_empty() {
+        run_statements(stdin_statements(), &opts.mode_name(), settings, opts.echo, opts.output, check)
+    } else {
+        run_statements(expressions.iter().cloned(), &opts.mode_name(), settings, opts.echo, opts.output, check)
+    }
+}
 
-    if opts.float_calc.is_some() {
-        calculate_float(&opts.float_calc.unwrap())
+/// Options from before the subcommands, the first one given is run.
+fn run_options(opts: &Opts, settings: Settings) {
+    if opts.calculate.is_some() {
+        calculate_in_mode(opts.calculate.as_ref().unwrap(), &opts.mode_name(), &settings, opts.output, opts.check)
+    } else if opts.float_calc.is_some() {
+        calculate_in_mode(opts.float_calc.as_ref().unwrap(), &opts.engine.to_string(), &settings, opts.output, opts.check)
+    } else if opts.compare.is_some() {
+        compare_engines(opts.compare.as_ref().unwrap(), opts.tolerance, &settings, opts.output, opts.check)
     } else if opts.bool_calc.is_some() {
-        calculate_bool(&opts.bool_calc.unwrap())
+        calculate_in_mode(opts.bool_calc.as_ref().unwrap(), "bool", &settings, opts.output, opts.check)
+    } else if opts.mixed_calc.is_some() {
+        calculate_in_mode(opts.mixed_calc.as_ref().unwrap(), "mixed", &settings, opts.output, opts.check)
+    } else if opts.truth_table.is_some() {
+        print_truth_table(opts.truth_table.as_ref().unwrap(), opts.csv, &settings, opts.output, opts.check)
+    } else if opts.cnf.is_some() {
+        analyze_bool(opts.cnf.as_ref().unwrap(), &settings, opts.output, opts.check, "expression", |table| { Result::Ok(boolean_forms::cnf(table)) })
+    } else if opts.dnf.is_some() {
+        analyze_bool(opts.dnf.as_ref().unwrap(), &settings, opts.output, opts.check, "expression", |table| { Result::Ok(boolean_forms::dnf(table)) })
+    } else if opts.minimize.is_some() {
+        analyze_bool(opts.minimize.as_ref().unwrap(), &settings, opts.output, opts.check, "expression", boolean_forms::minimize)
+    } else if opts.satisfiable.is_some() {
+        analyze_bool(opts.satisfiable.as_ref().unwrap(), &settings, opts.output, opts.check, "verdict", |table| {
+            Result::Ok(match boolean_forms::satisfying_assignment(table) {
+                Some(assignment) => format!("satisfiable: {}", assignment),
+                None => "unsatisfiable".to_string(),
+            })
+        })
+    } else if opts.tautology.is_some() {
+        analyze_bool(opts.tautology.as_ref().unwrap(), &settings, opts.output, opts.check, "verdict", |table| {
+            Result::Ok(match boolean_forms::falsifying_assignment(table) {
+                Some(assignment) => format!("not a tautology, counterexample: {}", assignment),
+                None => "tautology".to_string(),
+            })
+        })
+    } else if !opts.equivalent.is_empty() {
+        check_equivalence(&opts.equivalent[0], &opts.equivalent[1], &settings, opts.output, opts.check)
+    } else if opts.decimal_calc.is_some() {
+        calculate_in_mode(opts.decimal_calc.as_ref().unwrap(), "decimal", &settings, opts.output, opts.check)
+    } else if opts.matrix_calc.is_some() {
+        calculate_in_mode(opts.matrix_calc.as_ref().unwrap(), "matrix", &settings, opts.output, opts.check)
+    } else if opts.file.is_some() {
+        run_script(opts.file.as_ref().unwrap(), &opts.mode_name(), &settings, opts.quiet, opts.keep_going, opts.output, opts.check)
+    } else if opts.modes {
+        print_modes()
     } else if opts.list {
-        print_operators()
+        print_operators(opts.mode.as_deref(), &settings)
+    } else if opts.batch || (opts.interactive && !atty::is(Stream::Stdin)) {
+        run_statements(stdin_statements(), &opts.mode_name(), &settings, opts.echo, opts.output, opts.check)
     } else if opts.interactive {
-        print_interactive_help();
-        loop {
-            let mut buffer = String::new();
-            print!("> ");
-            stdout().flush().unwrap();
-            std::io::stdin().read_line(&mut buffer).unwrap();
-            buffer = String::from(buffer.trim());
-            match buffer.as_str() {
-                "" => print_interactive_help(),
-                "exit" => exit(0),
-                "list" => print_operators(),
-                // it if it.starts_with("add") => ,
-                _ => calculate_float(&buffer)
+        run_repl(&opts.mode_name(), settings)
+    } else if !atty::is(Stream::Stdin) {
+        run_statements(stdin_statements(), &opts.mode_name(), &settings, opts.echo, opts.output, opts.check)
+    } else {
+        println!("Use flag --help for usage information")
+    }
+}
+
+/// Interactive shell, statements are read until 'exit' or the end of the input.
+fn run_repl(name: &str, mut settings: Settings) {
+    let mut mode = match registry::find(name) {
+        Ok(mode) => mode,
+        Err(message) => usage_error(&message, Output::Text),
+    };
+    let mut shell = Shell::new(mode.create(&settings));
+    // inputs and printed values of the numbered results
+    let mut history: Vec<(String, String)> = Vec::new();
+    print_interactive_help();
+    loop {
+        let buffer = match shell.read_statement() {
+            Some(buffer) => buffer,
+            None => {
+                println!();
+                exit(0)
+            }
+        };
+        match buffer.as_str() {
+            "" => print_interactive_help(),
+            "exit" => exit(0),
+            "list" => print_operations(shell.calculator()),
+            "history" => print_history(&history),
+            "history clear" => {
+                history.clear();
+                shell.calculator().clear_results();
+            }
+            it if it.starts_with(':') => match split_command(it) {
+                (":reset", "") => {
+                    shell.set_calculator(mode.create(&settings));
+                    history.clear();
+                    println!("Variables, results and history are cleared");
+                }
+                (":help", name) => print_help(name, shell.calculator()),
+                (":set", "") => print_settings(mode, &settings),
+                (":set", command) => match apply_setting(command, mode, settings) {
+                    Ok((changed_mode, changed_settings)) => {
+                        reconfigure(&mut shell, (mode, settings), changed_mode, changed_settings);
+                        mode = changed_mode;
+                        settings = changed_settings;
+                        print_settings(mode, &settings);
+                    }
+                    Err(message) => println!("[error] {}", message),
+                },
+                (":save", path) => match save_session(path, mode, shell.calculator()) {
+                    Ok(count) => println!("Saved {} variable{} to {}", count, if count == 1 { "" } else { "s" }, path),
+                    Err(message) => println!("[error] {}", message),
+                },
+                (":load", path) => mode = load_session(path, &mut shell, mode, settings),
+                (":engine", name) => {
+                    let changed_mode = switch_engine(name, mode);
+                    reconfigure(&mut shell, (mode, settings), changed_mode, settings);
+                    mode = changed_mode;
+                }
+                (":mode", name) => {
+                    let changed_mode = switch_mode(name, mode);
+                    reconfigure(&mut shell, (mode, settings), changed_mode, settings);
+                    mode = changed_mode;
+                }
+                (":format", options) => {
+                    settings.format = switch_format(options, settings.format);
+                    shell.calculator().set_number_format(settings.format);
+                }
+                (":base", base) => {
+                    settings.format = switch_format(&if base.is_empty() { String::new() } else { format!("base {}", base) }, settings.format);
+                    shell.calculator().set_number_format(settings.format);
+                }
+                _ => println!("[error] unknown command '{}', ':help' lists the commands", it),
+            },
+            // it if it.starts_with("add") => ,
+            _ => {
+                if let Some(value) = execute(&buffer, shell.calculator(), history.len() + 1) {
+                    history.push((buffer, value));
+                }
             }
         }
+    }
+}
+
+/// Splits a shell command into its name, the first word, and the trimmed rest.
+fn split_command(buffer: &str) -> (&str, &str) {
+    let buffer = buffer.trim();
+    return match buffer.find(char::is_whitespace) {
+        Some(index) => (&buffer[..index], buffer[index..].trim()),
+        None => (buffer, ""),
+    };
+}
+
+/// Switches from the `current` mode and settings to `mode` and `settings`. A change of the number format only
+/// keeps the calculator, any other change needs a new one.
+fn reconfigure(shell: &mut Shell, current: (Mode, Settings), mode: Mode, settings: Settings) {
+    let same_calculator = Settings { format: settings.format, ..current.1 } == settings;
+    if mode.name == current.0.name && same_calculator {
+        shell.calculator().set_number_format(settings.format);
     } else {
-        println!("Use flag --help for usage information")
+        recreate(shell, mode, &settings);
+    }
+}
+
+/// Gives the shell a calculator of `mode` with `settings` which takes over the variables and results.
+fn recreate(shell: &mut Shell, mode: Mode, settings: &Settings) {
+    let mut calculator = mode.create(settings);
+    let dropped = carry_over(shell.calculator(), calculator.as_mut());
+    shell.set_calculator(calculator);
+    if !dropped.is_empty() {
+        println!("[error] not carried over to the {} mode: {}", mode.name, dropped.join(", "));
+    }
+}
+
+/// Assigns the variables of `from` in `to` and keeps its results again under their numbers, so that they still
+/// match the history. `ans` is the newest result `to` could read. Gives the names which could not be carried over.
+fn carry_over(from: &dyn AnyCalculator, to: &mut dyn AnyCalculator) -> Vec<String> {
+    // a value may not be read by another mode, e.g. a matrix by the float mode
+    let mut dropped: Vec<String> = from.variables().into_iter()
+        .filter(|(name, value)| to.execute(&format!("{} = {}", name, value)).is_err())
+        .map(|(name, _)| name)
+        .collect();
+    for (number, value) in from.results() {
+        match to.execute(&value) {
+            Ok(_) => to.keep_result(number),
+            Err(_) => dropped.push(format!("_{}", number)),
+        }
     }
+
+    return dropped;
 }
 
-fn print_operators() {
-    println!("Supported operations");
-    let calculator = float_calculator::float_calculator();
+/// Reads a line of stdin, and the following ones while a line ends with a backslash. `None` at the end of the input.
+fn read_statement() -> Option<String> {
+    let mut buffer = String::new();
+    if std::io::stdin().read_line(&mut buffer).unwrap_or(0) == 0 {
+        return None;
+    }
+
+    while buffer.trim_end().ends_with('\\') {
+        if std::io::stdin().read_line(&mut buffer).unwrap_or(0) == 0 {
+            break;
+        }
+    }
+
+    return Some(String::from(buffer.trim()));
+}
+
+/// Lines of stdin, a line ending with a backslash is continued on the next one.
+fn stdin_statements() -> impl Iterator<Item = String> {
+    return std::iter::from_fn(read_statement);
+}
+
+/// Runs statements one after another: results go to stdout, errors to stderr. JSON output puts both on stdout.
+/// Exits with the status of the first failed statement.
+fn run_statements(statements: impl Iterator<Item = String>, name: &str, settings: &Settings, echo: bool, output: Output, check: bool) {
+    let mut calculator = match registry::find(name) {
+        Ok(mode) => mode.create(settings),
+        Err(message) => usage_error(&message, output),
+    };
+
+    let mut status = 0;
+    for line in statements {
+        if line.is_empty() {
+            continue;
+        }
+        let outcome = match calculator.execute(&line) {
+            Ok(outcome) => outcome,
+            Err(diagnostic) => {
+                report_error(&line, &diagnostic, output);
+                if status == 0 {
+                    status = exit_status(&diagnostic);
+                }
+                continue;
+            }
+        };
+        if check {
+            continue;
+        }
+        if output == Output::Json {
+            println!("{}", json_outcome(&line, &outcome));
+            continue;
+        }
+        let result = match outcome {
+            Outcome::Value { value, .. } => value,
+            Outcome::Assignment { name, value, .. } => format!("{} = {}", name, value),
+        };
+        if echo {
+            println!("{}\t{}", line, result);
+        } else {
+            println!("{}", result);
+        }
+    }
+    exit(status)
+}
+
+fn print_modes() {
+    println!("{:<15} {:<40}", "Mode", "Description");
+    for mode in registry::modes() {
+        println!("{:<15} {:<40}", mode.name, mode.description)
+    }
+}
+
+fn print_operators(name: Option<&str>, settings: &Settings) {
+    if name.is_some() {
+        match registry::find(name.unwrap()) {
+            Ok(mode) => print_operations(mode.create(settings).as_ref()),
+            Err(message) => usage_error(&message, Output::Text),
+        }
+        return;
+    }
+
+    for (index, mode) in registry::modes().iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        println!("Supported operations of mode '{}': {}", mode.name, mode.description);
+        print_operations(mode.create(settings).as_ref());
+    }
+}
+
+fn print_operations(calculator: &dyn AnyCalculator) {
     println!("{:<15} {:<40} {:<15}", "Syntax", "Description", "Priority (higher value=higher priority)");
-    for op in calculator.operations {
+    for op in calculator.operations() {
         println!(
             "{:<15} {:<40} {:<15}",
-            op.pretty(),
-            op.description(),
-            op.priority()
+            op.syntax,
+            op.description,
+            op.priority
         )
     }
 }
 
 fn print_interactive_help() {
-    println!("Type 'exit' for exit and 'list' for supported operations list.")
+    println!("Type an expression, or 'x = <expr>' to assign a variable. Commands:");
+    for (usage, description) in SHELL_COMMANDS.iter() {
+        println!("  {:<22} {}", usage, description);
+    }
+    println!("Results are numbered, 'ans' or '_' is the last one and '_2' the second.");
+    println!("Arrow keys browse the history, Ctrl-R searches it and Tab completes names of functions, constants and variables.")
+}
+
+/// `:help <name>`: usage of a shell command, or syntax, description and priority of the operations spelled `name`.
+fn print_help(name: &str, calculator: &dyn AnyCalculator) {
+    if name.is_empty() {
+        print_interactive_help();
+        return;
+    }
+
+    let command = name.trim_start_matches(':');
+    let usage = SHELL_COMMANDS.iter().find(|(usage, _)| {
+        usage.trim_start_matches(':').split(' ').next() == Some(command)
+    });
+    if let Some((usage, description)) = usage {
+        println!("{}  {}", usage, description);
+        if command == "set" {
+            println!("Modes: {}. Rounding: half-even or half-up. Angle: {}.", mode_names().join(", "), AngleUnit::VARIANTS.join(" or "));
+        }
+        return;
+    }
+
+    let help = operation_help(calculator, name);
+    if help.is_empty() {
+        println!("[error] no command or operation '{}' in this mode, 'list' shows the operations", name);
+        return;
+    }
+    println!("{}", help.join("\n\n"));
+}
+
+/// Syntax, description and priority of each operation which has `name` as its signature or alias.
+fn operation_help(calculator: &dyn AnyCalculator, name: &str) -> Vec<String> {
+    return calculator.operations().iter()
+        .filter(|it| it.spellings.iter().any(|spelling| spelling == name))
+        .map(|it| format!("Syntax:      {}\nDescription: {}\nPriority:    {}", it.syntax, it.description, it.priority))
+        .collect();
+}
+
+fn print_settings(mode: Mode, settings: &Settings) {
+    println!("mode       {}", mode.name);
+    println!("precision  {} bits", settings.precision);
+    println!("scale      {}", settings.scale);
+    println!("rounding   {}", settings.rounding);
+    println!("angle      {}", settings.angle);
+    println!("format     {}", settings.format.to_string());
+}
+
+/// `:set <setting> <value>` gives the mode and the settings with the setting changed.
+fn apply_setting(command: &str, mode: Mode, settings: Settings) -> Result<(Mode, Settings), String> {
+    let (name, value) = match command.find(char::is_whitespace) {
+        Some(index) => (&command[..index], command[index..].trim()),
+        None => (command, ""),
+    };
+    if value.is_empty() {
+        return Result::Err(format!("expected a value after '{}'", name));
+    }
+
+    let mut changed = settings;
+    match name {
+        "mode" => return registry::find(value).map(|it| (it, settings)),
+        "precision" => {
+            changed.precision = value.parse().ok().filter(|it| PRECISIONS.contains(it))
+                .ok_or("precision must be from 2 to 65536 bits".to_string())?;
+        }
+        "scale" => changed.scale = value.parse().map_err(|_| format!("invalid scale '{}'", value))?,
+        "rounding" => changed.rounding = Rounding::from_str(value).map_err(|_| format!("unknown rounding '{}', expected half-even or half-up", value))?,
+        "angle" => {
+            changed.angle = AngleUnit::from_str(value).map_err(|_| {
+                format!("unknown angle unit '{}', expected one of: {}", value, AngleUnit::VARIANTS.join(", "))
+            })?;
+        }
+        "format" => changed.format = settings.format.apply(value)?,
+        _ => return Result::Err(format!("unknown setting '{}', expected one of: mode, precision, scale, rounding, angle, format", name)),
+    }
+
+    return Result::Ok((mode, changed));
 }
 
-fn calculate_float(buffer: &str) {
-    calculate(buffer, float_calculator::float_calculator())
+/// `:save <path>` writes the variables as a script of assignments, gives how many were written.
+fn save_session(path: &str, mode: Mode, calculator: &dyn AnyCalculator) -> Result<usize, String> {
+    if path.is_empty() {
+        return Result::Err("expected a file name after ':save'".to_string());
+    }
+
+    let variables = calculator.variables();
+    let mut script = format!("{}{}\n", SESSION_HEADER, mode.name);
+    for (name, value) in variables.iter() {
+        script.push_str(&format!("{} = {}\n", name, value));
+    }
+    std::fs::write(path, script).map_err(|error| format!("can't write '{}': {}", path, error))?;
+
+    return Result::Ok(variables.len());
 }
 
-fn calculate_bool(buffer: &str) {
-    calculate(buffer, bool_calculator::boolean_calculator())
+/// Mode named in the first line of a script written by `:save`.
+fn saved_mode(source: &str) -> Option<&str> {
+    return source.lines().next()?.strip_prefix(SESSION_HEADER).map(str::trim);
 }
 
-fn calculate<T: 'static + Clone + Display>(buffer: &str, calculator: ExprCalculator<T>){
-    let result = calculator.calculate(&buffer);
+/// `:load <path>` runs the statements of a script, such as one written by `:save`, and prints their values.
+/// The shell is switched to the mode the script was saved in first, gives the mode of the shell.
+fn load_session(path: &str, shell: &mut Shell, mode: Mode, settings: Settings) -> Mode {
+    if path.is_empty() {
+        println!("[error] expected a file name after ':load'");
+        return mode;
+    }
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => {
+            println!("[error] can't read '{}': {}", path, error);
+            return mode;
+        }
+    };
+    let mode = match saved_mode(&source) {
+        Some(name) if name != mode.name => {
+            let saved = switch_mode(name, mode);
+            reconfigure(shell, (mode, settings), saved, settings);
+            saved
+        }
+        _ => mode,
+    };
+
+    for statement in script::statements(&source) {
+        match shell.calculator().execute(&statement.text) {
+            Ok(Outcome::Assignment { name, value, .. }) => println!("{} = {}", name, value),
+            Ok(Outcome::Value { value, .. }) => println!("{}", value),
+            Err(diagnostic) => {
+                let diagnostic = Diagnostic { pos: diagnostic.pos + statement.offset, ..diagnostic }.locate(&source);
+                print_script_diagnostic(path, &source, &diagnostic);
+            }
+        }
+    }
+
+    return mode;
+}
+
+fn mode_names() -> Vec<&'static str> {
+    return registry::modes().iter().map(|it| it.name).collect();
+}
+
+fn switch_engine(name: &str, current: Mode) -> Mode {
+    if name.is_empty() {
+        println!("Engine: {}", current.name);
+        return current;
+    }
+
+    return match Engine::from_str(name) {
+        Ok(engine) => switch_mode(&engine.to_string(), current),
+        Err(_) => {
+            println!("Unknown engine '{}', expected one of: {}", name, Engine::VARIANTS.join(", "));
+            current
+        }
+    };
+}
+
+fn switch_mode(name: &str, current: Mode) -> Mode {
+    if name.is_empty() {
+        println!("Mode: {}", current.name);
+        return current;
+    }
+
+    return match registry::find(name) {
+        Ok(mode) => {
+            println!("Mode: {}", mode.name);
+            mode
+        }
+        Err(message) => {
+            println!("[error] {}", message);
+            current
+        }
+    };
+}
+
+fn switch_format(command: &str, current: NumberFormat) -> NumberFormat {
+    if command.is_empty() {
+        println!("Format: {}", current.to_string());
+        return current;
+    }
+
+    return match current.apply(command) {
+        Ok(format) => {
+            println!("Format: {}", format.to_string());
+            format
+        }
+        Err(message) => {
+            println!("[error] {}", message);
+            current
+        }
+    };
+}
+
+fn calculate_in_mode(buffer: &str, name: &str, settings: &Settings, output: Output, check: bool) {
+    let calculator = match registry::find(name) {
+        Ok(mode) => mode.create(settings),
+        Err(message) => usage_error(&message, output),
+    };
+
+    let result = match output {
+        Output::Text => calculator.calculate(buffer),
+        Output::Json => calculator.answer(buffer).map(|it| json_outcome(buffer, &it)),
+    };
+    match result {
+        Ok(_) if check => (),
+        Ok(printed) => println!("{}", printed),
+        Err(diagnostic) => {
+            report_error(buffer, &diagnostic, output);
+            exit(exit_status(&diagnostic))
+        }
+    }
+}
+
+/// Reports a wrong command line and exits.
+fn usage_error(message: &str, output: Output) -> ! {
+    match output {
+        Output::Text => eprintln!("[error] {}", message),
+        Output::Json => println!("{}", json_usage_error(message)),
+    }
+    exit(USAGE_ERROR)
+}
+
+fn exit_status(diagnostic: &Diagnostic) -> i32 {
+    return if diagnostic.is_failure() { EVALUATION_ERROR } else { SYNTAX_ERROR };
+}
+
+/// Stops at the first error unless `keep_going`, exits with the status of the first error.
+fn run_script(path: &str, name: &str, settings: &Settings, quiet: bool, keep_going: bool, output: Output, check: bool) {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => usage_error(&format!("can't read '{}': {}", path, error), output),
+    };
+    let mut calculator = match registry::find(name) {
+        Ok(mode) => mode.create(settings),
+        Err(message) => usage_error(&message, output),
+    };
+
+    let mut status = 0;
+    let mut last = None;
+    for statement in script::statements(&source) {
+        match calculator.execute(&statement.text) {
+            Ok(_) if check => (),
+            // text output only prints the values of expressions
+            Ok(Outcome::Assignment { .. }) if output == Output::Text => (),
+            Ok(outcome) => {
+                let printed = match (output, outcome) {
+                    (Output::Text, Outcome::Value { value, .. }) => value,
+                    (_, outcome) => json_outcome(&statement.text, &outcome),
+                };
+                if quiet {
+                    last = Some(printed);
+                } else {
+                    println!("{}", printed);
+                }
+            }
+            Err(diagnostic) => {
+                let start = diagnostic.pos;
+                let diagnostic = Diagnostic { pos: diagnostic.pos + statement.offset, ..diagnostic }.locate(&source);
+                match output {
+                    Output::Text => print_script_diagnostic(path, &source, &diagnostic),
+                    Output::Json => println!("{}", json_error(&statement.text, start, &diagnostic)),
+                }
+                if status == 0 {
+                    status = exit_status(&diagnostic);
+                }
+                if !keep_going {
+                    exit(status)
+                }
+            }
+        }
+    }
+    if let Some(printed) = last {
+        println!("{}", printed);
+    }
+    exit(status)
+}
+
+fn compare_engines(buffer: &str, tolerance: f64, settings: &Settings, output: Output, check: bool) {
+    let precise = registry::float_mode(settings).calculate(buffer);
+    if precise.is_err() {
+        print_err(buffer, precise, output);
+    }
+    let fast = registry::f64_mode(settings).calculate(buffer);
+    if fast.is_err() {
+        print_err(buffer, fast, output);
+    }
+
+    let precise = precise.ok().unwrap();
+    let fast = fast.ok().unwrap();
+    let (difference, same) = engine::compare(&precise, fast, tolerance);
+
+    let verdict = if same { "within" } else { "DIFFERS beyond" };
+    let report = format!("f64:   {}\nfloat: {}\ndifference: {:e}, {} tolerance {:e}", fast, precise, difference, verdict, tolerance);
+    print_answer(buffer, report, "comparison", output, check);
+}
+
+fn print_truth_table(buffer: &str, csv: bool, settings: &Settings, output: Output, check: bool) {
+    let result = truth_table::truth_table(&registry::bool_mode(settings), buffer);
+
+    if result.is_err() {
+        print_err(buffer, result, output);
+    }
+
+    let table = result.ok().unwrap();
+    print_answer(buffer, if csv { table.to_csv() } else { table.to_text() }, "table", output, check);
+}
+
+/// Prints what `action` tells about the truth table of `buffer`, an error of the action is an evaluation error.
+fn analyze_bool(
+    buffer: &str,
+    settings: &Settings,
+    output: Output,
+    check: bool,
+    type_name: &'static str,
+    action: impl Fn(&TruthTable) -> Result<String, String>,
+) {
+    let result = truth_table::truth_table(&registry::bool_mode(settings), buffer);
 
     if result.is_err() {
-        print_err(buffer, result);
+        print_err(buffer, result, output);
+    }
+
+    match action(&result.ok().unwrap()) {
+        Ok(answer) => print_answer(buffer, answer, type_name, output, check),
+        Err(message) => {
+            let diagnostic = Diagnostic { failure: true, ..Diagnostic::new(0, buffer.to_string(), message) }.locate(buffer);
+            report_error(buffer, &diagnostic, output);
+            exit(EVALUATION_ERROR)
+        }
+    }
+}
+
+fn check_equivalence(left: &str, right: &str, settings: &Settings, output: Output, check: bool) {
+    let result = boolean_forms::counterexample(&registry::bool_mode(settings), left, right);
+
+    let answer = match result {
+        Ok(None) => "equivalent".to_string(),
+        Ok(Some(assignment)) => format!("not equivalent, counterexample: {}", assignment),
+        Err((buffer, token)) => print_err(&buffer, Result::<(), Token<bool>>::Err(token), output),
+    };
+    print_answer(&format!("{} <-> {}", left, right), answer, "verdict", output, check);
+}
+
+/// Prints the `value` an option gave for `buffer`, nothing when the expression is only checked.
+fn print_answer(buffer: &str, value: String, type_name: &'static str, output: Output, check: bool) {
+    if check {
         return;
     }
 
-    println!("{}", result.ok().unwrap().to_string());
+    match output {
+        Output::Text => println!("{}", value),
+        Output::Json => println!("{}", json_outcome(buffer, &Outcome::Value { value, type_name })),
+    }
+}
+
+/// Runs a statement of the interactive shell and keeps its value as result `number`, gives the printed value.
+fn execute(buffer: &str, calculator: &mut dyn AnyCalculator, number: usize) -> Option<String> {
+    let (name, value) = match calculator.execute(buffer) {
+        Ok(Outcome::Value { value, .. }) => (None, value),
+        Ok(Outcome::Assignment { name, value, .. }) => (Some(name), value),
+        Err(diagnostic) => {
+            print_diagnostic(buffer, &diagnostic);
+            return None;
+        }
+    };
+    calculator.keep_result(number);
+    match name {
+        Some(name) => println!("[{}] {} = {}", number, name, value),
+        None => println!("[{}] = {}", number, value),
+    }
+
+    return Some(value);
+}
+
+fn print_history(history: &[(String, String)]) {
+    for (index, (input, value)) in history.iter().enumerate() {
+        println!("[{}] {}  ->  {}", index + 1, input.replace("\\\n", " "), value);
+    }
+}
+
+/// Prints the error of a failed `result` and exits.
+fn print_err<T, V: 'static + Clone>(buffer: &str, result: Result<T, Token<V>>, output: Output) -> ! {
+    let diagnostic = Diagnostic::from(result.err().unwrap()).locate(buffer);
+    report_error(buffer, &diagnostic, output);
+    exit(exit_status(&diagnostic))
+}
+
+/// Error of a statement of the script at `path`, `diagnostic` is located in the whole `source`.
+fn print_script_diagnostic(path: &str, source: &str, diagnostic: &Diagnostic) {
+    eprintln!("{}:{}:{}: [{}] {}", path, diagnostic.line, diagnostic.column, diagnostic.kind(), diagnostic.describe());
+    eprint!("{}", caret(source, diagnostic));
+}
+
+fn print_diagnostic(buffer: &str, diagnostic: &Diagnostic) {
+    eprint!("{}", render_diagnostic(buffer, diagnostic));
+}
+
+fn report_error(buffer: &str, diagnostic: &Diagnostic, output: Output) {
+    match output {
+        Output::Text => print_diagnostic(buffer, diagnostic),
+        Output::Json => println!("{}", json_error(buffer, diagnostic.pos, diagnostic)),
+    }
+}
+
+fn render_diagnostic(buffer: &str, diagnostic: &Diagnostic) -> String {
+    return format!("{}[{}] {}\n", caret(buffer, diagnostic), diagnostic.kind(), diagnostic.to_string());
 }
 
-fn print_err<T: 'static + Clone + Display>(buffer: &str, result: Result<T, Token<T>>) {
-    let err = &result.err().unwrap();
-    println!("{}", buffer);
-    println!(" {:>1$}", "^", err.get_pos());
-    println!("[syntax error] {}", err.to_string());
-}
\ No newline at end of file
+/// The line of the error with a caret under it.
+fn caret(buffer: &str, diagnostic: &Diagnostic) -> String {
+    let line = buffer.split('\n').nth(diagnostic.line - 1).unwrap_or("").trim_end_matches('\r');
+    // the caret is placed by display width, so it stays under the token after wide or multi-byte characters,
+    // tabs are copied as they are
+    let offset: String = line.chars()
+        .take(diagnostic.column - 1)
+        .map(|it| if it == '\t' { "\t".to_string() } else { " ".repeat(it.width().unwrap_or(0)) })
+        .collect();
+
+    return format!("{}\n{}^\n", line, offset);
+}
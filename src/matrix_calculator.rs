@@ -0,0 +1,144 @@
+use crate::ast::{ExprCalculator, HIGHEST_ORDER, HIGH_ORDER, LOWEST_ORDER, LOW_ORDER, PrimitiveHandler};
+use crate::literal;
+use crate::tensor::Tensor;
+use std::str::FromStr;
+
+struct TensorHandler {
+    si_suffixes: bool,
+}
+
+impl TensorHandler {
+    /// Reads a scalar or an element of a vector or matrix as the other number modes read their literals.
+    fn number(&self, input: &str) -> Result<f64, String> {
+        let literal = literal::parse(input, self.si_suffixes)?;
+
+        return f64::from_str(&literal.to_scientific()).map_err(|it| it.to_string());
+    }
+
+    fn parse(&self, input: &str) -> Result<Tensor, Option<String>> {
+        return Tensor::parse(input, &|it| self.number(it));
+    }
+}
+
+impl PrimitiveHandler<Tensor> for TensorHandler {
+    fn from_string(&self, input: &String) -> Result<Tensor, ()> {
+        return self.parse(input).map_err(|_| ());
+    }
+
+    fn can_start_with(&self, input: String) -> bool {
+        if !input.starts_with('[') {
+            return literal::can_start_with(&input, self.si_suffixes);
+        }
+
+        let mut depth = 0;
+        for char in input.chars() {
+            if depth == 0 && char != '[' {
+                return false;
+            }
+            match char {
+                '[' => depth += 1,
+                ']' => depth -= 1,
+                // elements are whole literals, a malformed one is reported by `explain`
+                it if it.is_alphanumeric() || it.is_whitespace() || ",._+-".contains(it) => (),
+                _ => return false,
+            }
+        }
+
+        return true;
+    }
+
+    fn explain(&self, input: &String) -> Option<String> {
+        if !input.starts_with('[') {
+            return literal::parse(input, self.si_suffixes).err();
+        }
+
+        return self.parse(input).err().flatten();
+    }
+
+    fn enable_si_suffixes(&mut self) {
+        self.si_suffixes = true;
+    }
+}
+
+pub fn matrix_calculator() -> ExprCalculator<Tensor> {
+    let mut result = ExprCalculator::<Tensor>::new(Box::new(TensorHandler { si_suffixes: false }));
+
+    result.add_prefix(
+        "-".to_string(),
+        "Negation".to_string(),
+        Box::new(|op1| { op1.neg() }),
+        HIGH_ORDER,
+    );
+    result.add_fallible_infix(
+        "+".to_string(),
+        "Element-wise addition".to_string(),
+        Box::new(|op1, op2| { op1.add(&op2) }),
+        LOWEST_ORDER,
+    );
+    result.add_fallible_infix(
+        "-".to_string(),
+        "Element-wise subtraction".to_string(),
+        Box::new(|op1, op2| { op1.sub(&op2) }),
+        LOWEST_ORDER,
+    );
+    result.add_fallible_infix(
+        "*".to_string(),
+        "Matrix product".to_string(),
+        Box::new(|op1, op2| { op1.mul(&op2) }),
+        LOW_ORDER,
+    );
+    result.add_fallible_infix(
+        ".*".to_string(),
+        "Element-wise product".to_string(),
+        Box::new(|op1, op2| { op1.mul_elements(&op2) }),
+        LOW_ORDER,
+    );
+    result.add_fallible_infix(
+        "/".to_string(),
+        "Element-wise division".to_string(),
+        Box::new(|op1, op2| { op1.div_elements(&op2) }),
+        LOW_ORDER,
+    );
+    result.add_one_argument_function(
+        "transpose".to_string(),
+        "Transpose".to_string(),
+        Box::new(|op1| { op1.transpose() }),
+        HIGHEST_ORDER,
+    );
+    result.add_function(
+        "det".to_string(),
+        "Determinant".to_string(),
+        Box::new(|operands| { operands[0].det() }),
+        1,
+        HIGHEST_ORDER,
+    );
+    result.add_function(
+        "inv".to_string(),
+        "Inverse matrix".to_string(),
+        Box::new(|operands| { operands[0].inv() }),
+        1,
+        HIGHEST_ORDER,
+    );
+    result.add_function(
+        "dot".to_string(),
+        "Dot product of two vectors".to_string(),
+        Box::new(|operands| { operands[0].dot(&operands[1]) }),
+        2,
+        HIGHEST_ORDER,
+    );
+    result.add_function(
+        "cross".to_string(),
+        "Cross product of two 3-vectors".to_string(),
+        Box::new(|operands| { operands[0].cross(&operands[1]) }),
+        2,
+        HIGHEST_ORDER,
+    );
+    result.add_one_argument_function(
+        "norm".to_string(),
+        "Euclidean (Frobenius for matrices) norm".to_string(),
+        Box::new(|op1| { op1.norm() }),
+        HIGHEST_ORDER,
+    );
+
+    return result;
+}
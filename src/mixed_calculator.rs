@@ -0,0 +1,164 @@
+use crate::angle::AngleUnit;
+use crate::ast::{ExprCalculator, HIGHEST_ORDER, HIGH_ORDER, LOWEST_ORDER, LOW_ORDER, MEDIUM_ORDER, OperationType, PrimitiveHandler};
+use crate::conditions::add_comparisons;
+use crate::float_calculator::NUMBER_ALIASES;
+use crate::operation_executor::{FallibleBinaryOperationExecutor, OperationExecutor};
+use crate::literal;
+use crate::value::Value;
+use rug::Float;
+use rug::float::Constant;
+use rug::ops::Pow;
+
+/// Comparisons, see `conditions`, bind looser than arithmetic and tighter than boolean operations:
+/// `x + 1 > 3 & y == 2` is `((x + 1) > 3) & (y == 2)`.
+const OR_ORDER: u8 = LOWEST_ORDER - 8;
+const AND_ORDER: u8 = LOWEST_ORDER - 6;
+
+struct ValueHandler {
+    si_suffixes: bool,
+    precision: u32,
+}
+
+impl PrimitiveHandler<Value> for ValueHandler {
+    fn from_string(&self, input: &String) -> Result<Value, ()> {
+        match input.as_str() {
+            "true" => return Result::Ok(Value::Boolean(true)),
+            "false" => return Result::Ok(Value::Boolean(false)),
+            _ => (),
+        }
+
+        let literal = literal::parse(input, self.si_suffixes).map_err(|_| ())?;
+        let valid = Float::parse(literal.to_scientific());
+        if valid.is_err() {
+            return Result::Err(());
+        }
+
+        return Result::Ok(Value::Number(Float::with_val(self.precision, valid.unwrap())));
+    }
+
+    fn can_start_with(&self, input: String) -> bool {
+        return literal::can_start_with(&input, self.si_suffixes)
+            || "true".starts_with(&input)
+            || "false".starts_with(&input);
+    }
+
+    fn explain(&self, input: &String) -> Option<String> {
+        return literal::parse(input, self.si_suffixes).err();
+    }
+
+    fn enable_si_suffixes(&mut self) {
+        self.si_suffixes = true;
+    }
+}
+
+/// Numbers with a `precision` bit mantissa, `angle` is the unit of trigonometric functions.
+pub fn mixed_calculator_with(precision: u32, angle: AngleUnit) -> ExprCalculator<Value> {
+    let mut result = ExprCalculator::<Value>::new(Box::new(ValueHandler { si_suffixes: false, precision }));
+
+    result.add(
+        "-".to_string(),
+        "Negation".to_string(),
+        OperationType::Prefix,
+        number_function(|op1| { -op1 }),
+        1,
+        HIGH_ORDER,
+    );
+    result.add(
+        "!".to_string(),
+        "NOT".to_string(),
+        OperationType::Prefix,
+        Box::new(|operands| { Result::Ok(Value::Boolean(!operands[0].boolean()?)) }),
+        1,
+        HIGH_ORDER,
+    );
+
+    result.add_fallible_infix("+".to_string(), "Addition".to_string(), arithmetic(|op1, op2| { op1 + op2 }), LOWEST_ORDER);
+    result.add_fallible_infix("-".to_string(), "Subtraction".to_string(), arithmetic(|op1, op2| { op1 - op2 }), LOWEST_ORDER);
+    result.add_fallible_infix("*".to_string(), "Multiplication".to_string(), arithmetic(|op1, op2| { op1 * op2 }), LOW_ORDER);
+    result.add_fallible_infix("/".to_string(), "Division".to_string(), arithmetic(|op1, op2| { op1 / op2 }), LOW_ORDER);
+    result.add_fallible_right_infix("^".to_string(), "Power".to_string(), arithmetic(|op1, op2| { op1.pow(op2) }), MEDIUM_ORDER);
+
+    add_comparisons(
+        &mut result,
+        |op1, op2| { Result::Ok(op1.number()?.partial_cmp(op2.number()?)) },
+        |op1, op2| { op1.equals(op2) },
+        Value::Boolean,
+    );
+
+    result.add_lazy_infix(
+        "&".to_string(),
+        "AND, y is skipped when x is false".to_string(),
+        Box::new(|operands| { Result::Ok(Value::Boolean(operands[0].get()?.boolean()? && operands[1].get()?.boolean()?)) }),
+        AND_ORDER,
+    );
+    result.add_lazy_infix(
+        "|".to_string(),
+        "OR, y is skipped when x is true".to_string(),
+        Box::new(|operands| { Result::Ok(Value::Boolean(operands[0].get()?.boolean()? || operands[1].get()?.boolean()?)) }),
+        OR_ORDER,
+    );
+    result.add_lazy_function(
+        "if".to_string(),
+        "y if x is true, otherwise z".to_string(),
+        Box::new(|operands| { if operands[0].get()?.boolean()? { operands[1].get() } else { operands[2].get() } }),
+        3,
+        HIGHEST_ORDER,
+    );
+
+    result.add_function(
+        "min".to_string(),
+        "Minimum".to_string(),
+        Box::new(|operands| { Result::Ok(Value::Number(operands[0].number()?.clone().min(operands[1].number()?))) }),
+        2,
+        HIGHEST_ORDER,
+    );
+    result.add_function(
+        "max".to_string(),
+        "Maximum".to_string(),
+        Box::new(|operands| { Result::Ok(Value::Number(operands[0].number()?.clone().max(operands[1].number()?))) }),
+        2,
+        HIGHEST_ORDER,
+    );
+    result.add_function("sqrt".to_string(), "Square root".to_string(), number_function(|op1| { op1.sqrt() }), 1, HIGHEST_ORDER);
+    result.add_function("sin".to_string(), "Sine".to_string(), trigonometric(angle, |op1| { op1.sin() }), 1, HIGHEST_ORDER);
+    result.add_function("cos".to_string(), "Cosine".to_string(), trigonometric(angle, |op1| { op1.cos() }), 1, HIGHEST_ORDER);
+    result.add_function("ln".to_string(), "Natural logarithm".to_string(), number_function(|op1| { op1.ln() }), 1, HIGHEST_ORDER);
+    result.add_function("exp".to_string(), "Exponent".to_string(), number_function(|op1| { op1.exp() }), 1, HIGHEST_ORDER);
+    result.add_function("abs".to_string(), "Absolute value".to_string(), number_function(|op1| { op1.abs() }), 1, HIGHEST_ORDER);
+
+    result.add_constant(
+        "pi".to_string(),
+        "Constant Pi=3.1415...".to_string(),
+        Value::Number(Float::with_val(precision, Constant::Pi)),
+    );
+    result.add_constant(
+        "e".to_string(),
+        "Constant e=2.7182....".to_string(),
+        Value::Number(Float::with_val(precision, 1).exp()),
+    );
+
+    for (signature, alias) in NUMBER_ALIASES.iter().chain(&[("!", "¬"), ("&", "∧"), ("|", "∨")]) {
+        result.add_alias(signature, alias);
+    }
+
+    return result;
+}
+
+fn arithmetic(calculate: fn(Float, &Float) -> Float) -> Box<dyn FallibleBinaryOperationExecutor<Value>> {
+    return Box::new(move |op1: Value, op2: Value| {
+        Result::Ok(Value::Number(calculate(op1.number()?.clone(), op2.number()?)))
+    });
+}
+
+fn number_function(calculate: fn(Float) -> Float) -> Box<dyn OperationExecutor<Value>> {
+    return Box::new(move |operands: Vec<Value>| {
+        Result::Ok(Value::Number(calculate(operands[0].number()?.clone())))
+    });
+}
+
+/// Function of an angle in `angle` units.
+fn trigonometric(angle: AngleUnit, calculate: fn(Float) -> Float) -> Box<dyn OperationExecutor<Value>> {
+    return Box::new(move |operands: Vec<Value>| {
+        Result::Ok(Value::Number(calculate(angle.to_radians(operands[0].number()?.clone()))))
+    });
+}
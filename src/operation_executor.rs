@@ -1,8 +1,10 @@
-pub trait OperationExecutor<T>: Fn(Vec<T>) -> T {
+use crate::ast::Thunk;
+
+pub trait OperationExecutor<T>: Fn(Vec<T>) -> Result<T, String> {
     fn clone_box<'a>(&self) -> Box<dyn 'a + OperationExecutor<T>> where Self: 'a;
 }
 
-impl<T, F: Fn(Vec<T>) -> T + Clone> OperationExecutor<T> for F {
+impl<T, F: Fn(Vec<T>) -> Result<T, String> + Clone> OperationExecutor<T> for F {
     fn clone_box<'a>(&self) -> Box<dyn 'a + OperationExecutor<T>> where Self: 'a, {
         Box::new(self.clone())
     }
@@ -32,6 +34,23 @@ impl<'a, T: 'a> Clone for Box<dyn 'a + BinaryOperationExecutor<T>> {
 }
 
 
+pub trait FallibleBinaryOperationExecutor<T>: Fn(T, T) -> Result<T, String> {
+    fn clone_box<'a>(&self) -> Box<dyn 'a + FallibleBinaryOperationExecutor<T>> where Self: 'a;
+}
+
+impl<T, F: Fn(T, T) -> Result<T, String> + Clone> FallibleBinaryOperationExecutor<T> for F {
+    fn clone_box<'a>(&self) -> Box<dyn 'a + FallibleBinaryOperationExecutor<T>> where Self: 'a, {
+        Box::new(self.clone())
+    }
+}
+
+impl<'a, T: 'a> Clone for Box<dyn 'a + FallibleBinaryOperationExecutor<T>> {
+    fn clone(&self) -> Self {
+        (**self).clone_box()
+    }
+}
+
+
 pub trait UnaryoperationExecutor<T>: Fn(T) -> T {
     fn clone_box<'a>(&self) -> Box<dyn 'a + UnaryoperationExecutor<T>> where Self: 'a;
 }
@@ -46,4 +65,20 @@ impl<'a, T: 'a> Clone for Box<dyn 'a + UnaryoperationExecutor<T>> {
     fn clone(&self) -> Self {
         (**self).clone_box()
     }
-}
\ No newline at end of file
+}
+
+pub trait LazyOperationExecutor<T: Clone>: Fn(&[Thunk<T>]) -> Result<T, String> {
+    fn clone_box<'a>(&self) -> Box<dyn 'a + LazyOperationExecutor<T>> where Self: 'a;
+}
+
+impl<T: Clone, F: Fn(&[Thunk<T>]) -> Result<T, String> + Clone> LazyOperationExecutor<T> for F {
+    fn clone_box<'a>(&self) -> Box<dyn 'a + LazyOperationExecutor<T>> where Self: 'a, {
+        Box::new(self.clone())
+    }
+}
+
+impl<'a, T: 'a + Clone> Clone for Box<dyn 'a + LazyOperationExecutor<T>> {
+    fn clone(&self) -> Self {
+        (**self).clone_box()
+    }
+}
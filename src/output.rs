@@ -0,0 +1,73 @@
+use strum_macros::{Display, EnumString, EnumVariantNames};
+
+use crate::diagnostic::Diagnostic;
+use crate::registry::Outcome;
+
+/// How results and errors are printed by one-shot, batch and script runs.
+#[derive(Clone, Copy, PartialEq, Debug, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Output {
+    /// Results as they are, errors with a caret under the token
+    Text,
+    /// One JSON object per result or error, on its own line
+    Json,
+}
+
+/// `{"input": .., "value": .., "type": ..}`, assignments also give the `"variable"`.
+pub fn json_outcome(input: &str, outcome: &Outcome) -> String {
+    return match outcome {
+        Outcome::Value { value, type_name } => format!(
+            "{{\"input\": {}, \"value\": {}, \"type\": {}}}",
+            json_string(input), json_string(value), json_string(type_name)
+        ),
+        Outcome::Assignment { name, value, type_name } => format!(
+            "{{\"input\": {}, \"value\": {}, \"type\": {}, \"variable\": {}}}",
+            json_string(input), json_string(value), json_string(type_name), json_string(name)
+        ),
+    };
+}
+
+/// `{"input": .., "error": {..}}` with the error at char index `start` of `input`.
+/// Line and column are taken from the diagnostic, so a script can place them in the whole file.
+pub fn json_error(input: &str, start: usize, diagnostic: &Diagnostic) -> String {
+    let kind = if diagnostic.is_failure() { "evaluation" } else { "syntax" };
+    let message = match &diagnostic.message {
+        Some(message) => message.clone(),
+        None => format!("unexpected '{}'", diagnostic.value),
+    };
+
+    return format!(
+        "{{\"input\": {}, \"error\": {{\"kind\": \"{}\", \"message\": {}, \"token\": {}, \"start\": {}, \"end\": {}, \"line\": {}, \"column\": {}}}}}",
+        json_string(input),
+        kind,
+        json_string(&message),
+        json_string(&diagnostic.value),
+        start,
+        start + diagnostic.value.chars().count(),
+        diagnostic.line,
+        diagnostic.column
+    );
+}
+
+/// Error which is not about an expression, such as an unknown mode or a missing file.
+pub fn json_usage_error(message: &str) -> String {
+    return format!("{{\"error\": {{\"kind\": \"usage\", \"message\": {}}}}}", json_string(message));
+}
+
+fn json_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for char in value.chars() {
+        match char {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            it if (it as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", it as u32)),
+            it => quoted.push(it),
+        }
+    }
+    quoted.push('"');
+
+    return quoted;
+}
@@ -0,0 +1,109 @@
+use crate::ast::{ExprCalculator, HIGHEST_ORDER, HIGH_ORDER, LOWEST_ORDER, LOW_ORDER, MEDIUM_ORDER, PrimitiveHandler};
+use crate::conditions::add_conditions;
+use crate::literal;
+use rug::{Integer, Rational};
+use rug::ops::Pow;
+use std::cmp::Ordering;
+
+/// Largest numerator or denominator of a power, in bits. Larger ones take too long to build and print.
+const MAX_POWER_BITS: u64 = 1_000_000;
+
+struct RationalHandler {
+    si_suffixes: bool,
+}
+
+impl PrimitiveHandler<Rational> for RationalHandler {
+    /// Decimal literals are exact fractions: `0.1` is `1/10` and `1e-3` is `1/1000`.
+    fn from_string(&self, input: &String) -> Result<Rational, ()> {
+        let literal = literal::parse(input, self.si_suffixes).map_err(|_| ())?;
+        let (mantissa, exponent) = literal.to_mantissa_and_exponent();
+        let power = Integer::from(Integer::u_pow_u(10, exponent.abs() as u32));
+
+        if exponent < 0 {
+            return Result::Ok(Rational::from((mantissa, power)));
+        }
+        return Result::Ok(Rational::from((mantissa * power, Integer::from(1))));
+    }
+
+    fn can_start_with(&self, input: String) -> bool {
+        return literal::can_start_with(&input, self.si_suffixes);
+    }
+
+    fn explain(&self, input: &String) -> Option<String> {
+        return literal::parse(input, self.si_suffixes).err();
+    }
+
+    fn enable_si_suffixes(&mut self) {
+        self.si_suffixes = true;
+    }
+}
+
+/// Exact fractions: every operation keeps the full numerator and denominator.
+pub fn rational_calculator() -> ExprCalculator<Rational> {
+    let mut result = ExprCalculator::<Rational>::new(Box::new(RationalHandler { si_suffixes: false }));
+
+    result.add_prefix(
+        "-".to_string(),
+        "Negation".to_string(),
+        Box::new(|op1| { -op1 }),
+        HIGH_ORDER,
+    );
+    result.add_infix(
+        "+".to_string(),
+        "Addition".to_string(),
+        Box::new(|op1, op2| { op1 + op2 }),
+        LOWEST_ORDER,
+    );
+    result.add_infix(
+        "-".to_string(),
+        "Subtraction".to_string(),
+        Box::new(|op1, op2| { op1 - op2 }),
+        LOWEST_ORDER,
+    );
+    result.add_infix(
+        "*".to_string(),
+        "Multiplication".to_string(),
+        Box::new(|op1, op2| { op1 * op2 }),
+        LOW_ORDER,
+    );
+    result.add_fallible_infix(
+        "/".to_string(),
+        "Division".to_string(),
+        Box::new(|op1, op2| {
+            if op2.cmp0() == Ordering::Equal {
+                return Result::Err("division by zero".to_string());
+            }
+            return Result::Ok(op1 / op2);
+        }),
+        LOW_ORDER,
+    );
+    result.add_fallible_right_infix(
+        "^".to_string(),
+        "Integer power".to_string(),
+        Box::new(|op1, op2| {
+            let exponent = if op2.is_integer() { op2.numer().to_i32() } else { None };
+            if exponent.is_none() {
+                return Result::Err("exponent must be an integer".to_string());
+            }
+            if exponent.unwrap() < 0 && op1.cmp0() == Ordering::Equal {
+                return Result::Err("division by zero".to_string());
+            }
+            // at least this many bits, 1 and -1 stay small whatever the exponent
+            let bits = op1.numer().significant_bits().max(op1.denom().significant_bits()) - 1;
+            if u64::from(bits) * u64::from(exponent.unwrap().unsigned_abs()) > MAX_POWER_BITS {
+                return Result::Err("result is too large".to_string());
+            }
+            return Result::Ok(op1.pow(exponent.unwrap()));
+        }),
+        MEDIUM_ORDER,
+    );
+    result.add_one_argument_function(
+        "abs".to_string(),
+        "Absolute value".to_string(),
+        Box::new(|op1| { op1.abs() }),
+        HIGHEST_ORDER,
+    );
+    add_conditions(&mut result, |value| { value.cmp0() != Ordering::Equal }, |value| { Rational::from(value as i32) });
+
+    return result;
+}
@@ -0,0 +1,377 @@
+use crate::angle::AngleUnit;
+use crate::ast::{is_identifier, ExprCalculator, Span, TokenKind, Variables};
+use crate::bool_calculator::boolean_calculator;
+use crate::decimal::Rounding;
+use crate::decimal_calculator::decimal_calculator;
+use crate::diagnostic::Diagnostic;
+use crate::f64_calculator::f64_calculator_with;
+use crate::float_calculator::float_calculator_with;
+use crate::format::{Format, NumberFormat};
+use crate::matrix_calculator::matrix_calculator;
+use crate::mixed_calculator::mixed_calculator_with;
+use crate::rational_calculator::rational_calculator;
+use rug::Float;
+
+/// Options a mode may take into account when its calculator is created.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Settings {
+    /// Mantissa bits of the float and mixed modes
+    pub precision: u32,
+    pub angle: AngleUnit,
+    pub scale: u32,
+    pub rounding: Rounding,
+    pub currency: bool,
+    pub implicit_multiplication: bool,
+    pub si_suffixes: bool,
+    pub format: NumberFormat,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        return Settings {
+            precision: 64,
+            angle: AngleUnit::Rad,
+            scale: 2,
+            rounding: Rounding::HalfEven,
+            currency: false,
+            implicit_multiplication: false,
+            si_suffixes: false,
+            format: NumberFormat::default(),
+        };
+    }
+}
+
+/// Row of the supported operations list.
+pub struct OperationInfo {
+    /// The signature and its aliases, e.g. `*`, `×` and `·`
+    pub spellings: Vec<String>,
+    pub syntax: String,
+    pub description: String,
+    pub priority: u8,
+}
+
+/// What a statement did: gave a value or stored it in a variable. `type_name` is the type of the value.
+pub enum Outcome {
+    Value { value: String, type_name: &'static str },
+    Assignment { name: String, value: String, type_name: &'static str },
+}
+
+/// Calculator with its value type erased: takes an expression, gives a formatted result.
+pub trait AnyCalculator {
+    fn calculate(&self, input: &str) -> Result<String, Diagnostic>;
+    /// Like `calculate`, also tells the type of the result.
+    fn answer(&self, input: &str) -> Result<Outcome, Diagnostic>;
+    /// Runs an expression or an assignment `name = expression`, assigned variables are seen by later statements.
+    fn execute(&mut self, statement: &str) -> Result<Outcome, Diagnostic>;
+    fn operations(&self) -> Vec<OperationInfo>;
+    /// Names which can be typed: the ones of operations and of assigned variables, sorted.
+    fn names(&self) -> Vec<String>;
+    /// Keeps the value of the last executed statement as `ans`, `_` and `_<number>` for the following statements.
+    fn keep_result(&mut self, number: usize);
+    /// Forgets the kept results.
+    fn clear_results(&mut self);
+    /// Assigned variables but the kept results, sorted by name. Values are printed with all their digits,
+    /// so that `name = value` gives the variable back.
+    fn variables(&self) -> Vec<(String, String)>;
+    /// Kept results with their numbers, oldest first, printed like the values of `variables`.
+    fn results(&self) -> Vec<(usize, String)>;
+    /// Changes how later results are printed, variables and kept results stay.
+    fn set_number_format(&mut self, number_format: NumberFormat);
+    /// Tokens of a statement for syntax highlighting, see `ExprCalculator::spans`.
+    fn spans(&self, statement: &str) -> Vec<Span>;
+}
+
+struct TypedCalculator<T: Clone + Format> {
+    calculator: ExprCalculator<T>,
+    variables: Variables<T>,
+    /// Value of the last executed statement
+    last: Option<T>,
+    /// Numbers of the kept results
+    kept: Vec<usize>,
+    number_format: NumberFormat,
+    format: Box<dyn Fn(T, &NumberFormat) -> String>,
+}
+
+impl<T: 'static + Clone + Format> TypedCalculator<T> {
+    /// A trailing conversion such as `to hex` changes the format of this result only. Text after `to` which is
+    /// no conversion for `T` may still belong to the expression, e.g. `a | to & b` with a variable `to`.
+    fn evaluate(&self, input: &str) -> Result<(T, NumberFormat), Diagnostic> {
+        let (expression, number_format) = match self.number_format.split_conversion::<T>(input) {
+            Ok(it) => it,
+            Err((pos, message)) => {
+                return match self.calculator.calculate_with(input, &self.variables) {
+                    Ok(value) => Result::Ok((value, self.number_format)),
+                    Err(_) => Result::Err(Diagnostic::new(pos, input.chars().skip(pos).collect(), message).locate(input)),
+                };
+            }
+        };
+        let value = self.calculator.calculate_with(expression, &self.variables).map_err(|it| Diagnostic::from(it).locate(input))?;
+
+        return Result::Ok((value, number_format));
+    }
+}
+
+impl<T: 'static + Clone + Format> AnyCalculator for TypedCalculator<T> {
+    fn calculate(&self, input: &str) -> Result<String, Diagnostic> {
+        return self.evaluate(input).map(|(value, number_format)| (self.format)(value, &number_format));
+    }
+
+    fn answer(&self, input: &str) -> Result<Outcome, Diagnostic> {
+        let (value, number_format) = self.evaluate(input)?;
+        let type_name = value.type_name();
+
+        return Result::Ok(Outcome::Value { value: (self.format)(value, &number_format), type_name });
+    }
+
+    fn execute(&mut self, statement: &str) -> Result<Outcome, Diagnostic> {
+        let (name, offset) = match split_assignment(statement) {
+            Some(it) => it,
+            None => {
+                let (value, number_format) = self.evaluate(statement)?;
+                let type_name = value.type_name();
+                self.last = Some(value.clone());
+                return Result::Ok(Outcome::Value { value: (self.format)(value, &number_format), type_name });
+            }
+        };
+        if self.calculator.is_operation(&name) {
+            let pos = statement.chars().take_while(|it| it.is_whitespace()).count();
+            return Result::Err(Diagnostic::new(pos, name, "an operation can't be assigned".to_string()).locate(statement));
+        }
+
+        let expression: String = statement.chars().skip(offset).collect();
+        let (value, number_format) = self.evaluate(&expression).map_err(|it| {
+            Diagnostic { pos: it.pos + offset, ..it }.locate(statement)
+        })?;
+        let printed = (self.format)(value.clone(), &number_format);
+        let type_name = value.type_name();
+        self.last = Some(value.clone());
+        self.variables.insert(name.clone(), value);
+
+        return Result::Ok(Outcome::Assignment { name, value: printed, type_name });
+    }
+
+    fn operations(&self) -> Vec<OperationInfo> {
+        return self.calculator.operations.iter()
+            .filter(|it| !it.is_separator())
+            .map(|it| OperationInfo {
+                spellings: it.spellings().cloned().collect(),
+                syntax: it.pretty(),
+                description: it.description(),
+                priority: it.priority(),
+            })
+            .collect();
+    }
+
+    fn names(&self) -> Vec<String> {
+        let mut names = self.calculator.names();
+        names.extend(self.variables.keys().cloned());
+        names.sort();
+        names.dedup();
+
+        return names;
+    }
+
+    fn keep_result(&mut self, number: usize) {
+        if let Some(value) = self.last.take() {
+            for name in ["ans".to_string(), "_".to_string(), format!("_{}", number)].iter() {
+                self.variables.insert(name.clone(), value.clone());
+            }
+            self.kept.push(number);
+        }
+    }
+
+    fn clear_results(&mut self) {
+        for number in self.kept.drain(..) {
+            self.variables.remove(&format!("_{}", number));
+        }
+        self.variables.remove("ans");
+        self.variables.remove("_");
+    }
+
+    fn variables(&self) -> Vec<(String, String)> {
+        let mut kept: Vec<String> = self.kept.iter().map(|it| format!("_{}", it)).collect();
+        if !kept.is_empty() {
+            kept.extend(vec!["ans".to_string(), "_".to_string()]);
+        }
+        let mut variables: Vec<(String, String)> = self.variables.iter()
+            .filter(|(name, _)| !kept.contains(name))
+            .map(|(name, value)| (name.clone(), value.to_input()))
+            .collect();
+        variables.sort();
+
+        return variables;
+    }
+
+    fn results(&self) -> Vec<(usize, String)> {
+        return self.kept.iter()
+            .filter_map(|number| self.variables.get(&format!("_{}", number)).map(|value| (*number, value.to_input())))
+            .collect();
+    }
+
+    fn set_number_format(&mut self, number_format: NumberFormat) {
+        self.number_format = number_format;
+    }
+
+    fn spans(&self, statement: &str) -> Vec<Span> {
+        let mut spans = Vec::new();
+        let mut offset = 0;
+        if let Some((name, start)) = split_assignment(statement) {
+            let pos = statement.chars().take_while(|it| it.is_whitespace()).count();
+            let kind = if self.calculator.is_operation(&name) { TokenKind::Invalid } else { TokenKind::Variable };
+            spans.push(Span { kind, start: pos, end: pos + name.chars().count() });
+            spans.push(Span { kind: TokenKind::Operator, start: start - 1, end: start });
+            offset = start;
+        }
+
+        let input: String = statement.chars().skip(offset).collect();
+        let (expression, conversion) = match self.number_format.split_conversion::<T>(&input) {
+            Ok((expression, _)) if expression.len() == input.len() => (expression, None),
+            Ok((expression, _)) => (expression, Some(TokenKind::Operator)),
+            Err(_) if self.calculator.calculate_with(&input, &self.variables).is_ok() => (input.as_str(), None),
+            Err((pos, _)) => {
+                let before: String = input.chars().take(pos).collect();
+                let keyword = before.trim_end().strip_suffix("to").unwrap_or("").trim_end().len();
+                (&input[..keyword], Some(TokenKind::Invalid))
+            }
+        };
+        for span in self.calculator.spans(expression) {
+            spans.push(Span { start: span.start + offset, end: span.end + offset, ..span });
+        }
+        if let Some(kind) = conversion {
+            let start = expression.chars().count() + input[expression.len()..].chars().take_while(|it| it.is_whitespace()).count();
+            spans.push(Span { kind, start: start + offset, end: input.chars().count() + offset });
+        }
+
+        return spans;
+    }
+}
+
+/// Named calculator which can be chosen from the command line or the interactive shell.
+#[derive(Clone, Copy)]
+pub struct Mode {
+    pub name: &'static str,
+    pub description: &'static str,
+    factory: fn(&Settings) -> Box<dyn AnyCalculator>,
+}
+
+impl Mode {
+    pub fn create(&self, settings: &Settings) -> Box<dyn AnyCalculator> {
+        return (self.factory)(settings);
+    }
+}
+
+/// All modes, in the order they are listed. A new mode only needs to be added here.
+pub fn modes() -> Vec<Mode> {
+    return vec![
+        Mode {
+            name: "float",
+            description: "Arbitrary precision numbers (rug Float, 64 bit mantissa unless --precision)",
+            factory: |settings| { displayed(float_mode(settings), settings) },
+        },
+        Mode {
+            name: "f64",
+            description: "Hardware double precision numbers",
+            factory: |settings| { displayed(f64_mode(settings), settings) },
+        },
+        Mode {
+            name: "rational",
+            description: "Exact fractions",
+            factory: |settings| { displayed(configured(rational_calculator(), settings), settings) },
+        },
+        Mode {
+            name: "decimal",
+            description: "Exact decimal arithmetic with --scale and --rounding",
+            factory: |settings| {
+                let settings = *settings;
+                Box::new(TypedCalculator {
+                    calculator: configured(decimal_calculator(settings.scale, settings.rounding), &settings),
+                    variables: Variables::new(),
+                    last: None,
+                    kept: Vec::new(),
+                    number_format: settings.format,
+                    format: Box::new(move |result, format| {
+                        if settings.currency {
+                            result.round(2, settings.rounding).to_grouped_string()
+                        } else {
+                            result.round(settings.scale, settings.rounding).format(format)
+                        }
+                    }),
+                })
+            },
+        },
+        Mode {
+            name: "bool",
+            description: "Boolean expressions, literals true, false, 1 and 0",
+            factory: |settings| { displayed(bool_mode(settings), settings) },
+        },
+        Mode {
+            name: "mixed",
+            description: "Numbers, comparisons and booleans in one expression",
+            factory: |settings| { displayed(configured(mixed_calculator_with(settings.precision, settings.angle), settings), settings) },
+        },
+        Mode {
+            name: "matrix",
+            description: "Vectors and matrices",
+            factory: |settings| { displayed(configured(matrix_calculator(), settings), settings) },
+        },
+    ];
+}
+
+/// `name = expression` gives the name and the char index where the expression starts, `==` is a comparison.
+fn split_assignment(statement: &str) -> Option<(String, usize)> {
+    let index = statement.find('=')?;
+    if statement[index + 1..].starts_with('=') {
+        return None;
+    }
+
+    let name = statement[..index].trim();
+    if !is_identifier(name) {
+        return None;
+    }
+
+    return Some((name.to_string(), statement[..=index].chars().count()));
+}
+
+pub fn find(name: &str) -> Result<Mode, String> {
+    return modes().into_iter().find(|it| it.name == name).ok_or_else(|| {
+        let names: Vec<&str> = modes().iter().map(|it| it.name).collect();
+        format!("unknown mode '{}', expected one of: {}", name, names.join(", "))
+    });
+}
+
+/// Calculator of the `float` mode, for the options which work with its values rather than with printed results.
+pub fn float_mode(settings: &Settings) -> ExprCalculator<Float> {
+    return configured(float_calculator_with(settings.precision, settings.angle), settings);
+}
+
+/// Calculator of the `f64` mode, see `float_mode`.
+pub fn f64_mode(settings: &Settings) -> ExprCalculator<f64> {
+    return configured(f64_calculator_with(settings.angle), settings);
+}
+
+/// Calculator of the `bool` mode, see `float_mode`.
+pub fn bool_mode(settings: &Settings) -> ExprCalculator<bool> {
+    return configured(boolean_calculator(), settings);
+}
+
+fn displayed<T: 'static + Clone + Format>(calculator: ExprCalculator<T>, settings: &Settings) -> Box<dyn AnyCalculator> {
+    return Box::new(TypedCalculator {
+        calculator,
+        variables: Variables::new(),
+        last: None,
+        kept: Vec::new(),
+        number_format: settings.format,
+        format: Box::new(|result, format| result.format(format)),
+    });
+}
+
+fn configured<T: 'static + Clone>(mut calculator: ExprCalculator<T>, settings: &Settings) -> ExprCalculator<T> {
+    if settings.implicit_multiplication {
+        calculator.enable_implicit_multiplication("*");
+    }
+    if settings.si_suffixes {
+        calculator.enable_si_suffixes();
+    }
+
+    return calculator;
+}
@@ -0,0 +1,53 @@
+/// Statement of a script and the char index where it starts in the source, so errors can be located in the file.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Statement {
+    pub text: String,
+    pub offset: usize,
+}
+
+/// Splits `source` at `;` and line breaks. `#` starts a comment up to the end of the line, a backslash at
+/// the end of a line continues the statement on the next one. Blank statements are skipped.
+pub fn statements(source: &str) -> Vec<Statement> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut result = Vec::new();
+    let mut text = String::new();
+    let mut offset = 0;
+    let mut comment = false;
+    let mut continued = false;
+
+    for (pos, char) in chars.iter().enumerate() {
+        match char {
+            '\n' if continued => {
+                continued = false;
+                text.push(*char);
+            }
+            '\n' => {
+                comment = false;
+                push_statement(&mut result, &text, offset);
+                text.clear();
+                offset = pos + 1;
+            }
+            _ if comment => (),
+            ';' => {
+                push_statement(&mut result, &text, offset);
+                text.clear();
+                offset = pos + 1;
+            }
+            '#' => comment = true,
+            '\\' if chars[pos + 1..].iter().skip_while(|it| **it == '\r').next() == Some(&'\n') => {
+                continued = true;
+                text.push(*char);
+            }
+            it => text.push(*it),
+        }
+    }
+    push_statement(&mut result, &text, offset);
+
+    return result;
+}
+
+fn push_statement(statements: &mut Vec<Statement>, text: &str, offset: usize) {
+    if !text.trim().is_empty() {
+        statements.push(Statement { text: text.to_string(), offset });
+    }
+}
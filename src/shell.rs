@@ -0,0 +1,236 @@
+use std::borrow::Cow;
+use std::path::PathBuf;
+
+use atty::Stream;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{CompletionType, Config, Context, Editor, Helper};
+
+use crate::ast::{Span, TokenKind};
+use crate::registry::AnyCalculator;
+
+/// Words of the interactive shell which are not statements, commands starting with `:` aren't listed.
+const COMMANDS: [&str; 4] = ["exit", "list", "history", "history clear"];
+
+/// Line editor of the interactive shell: arrow keys, history kept between sessions, reverse search with Ctrl-R,
+/// completion of names with Tab and syntax highlighting of the calculator it owns.
+pub struct Shell {
+    editor: Editor<ShellHelper>,
+    history: Option<PathBuf>,
+}
+
+impl Shell {
+    /// Colors are only used when stdout is a terminal and `NO_COLOR` is not set.
+    pub fn new(calculator: Box<dyn AnyCalculator>) -> Shell {
+        let config = Config::builder()
+            .auto_add_history(false)
+            .history_ignore_space(true)
+            .completion_type(CompletionType::List)
+            .build();
+        let mut editor = Editor::with_config(config);
+        let colors = atty::is(Stream::Stdout) && std::env::var_os("NO_COLOR").is_none();
+        editor.set_helper(Some(ShellHelper { calculator, colors, continued: false }));
+
+        let history = history_path();
+        if let Some(path) = &history {
+            // there is no file before the first session
+            let _ = editor.load_history(path);
+        }
+
+        return Shell { editor, history };
+    }
+
+    pub fn calculator(&mut self) -> &mut dyn AnyCalculator {
+        return self.helper().calculator.as_mut();
+    }
+
+    /// Replaces the calculator, e.g. after the mode is switched.
+    pub fn set_calculator(&mut self, calculator: Box<dyn AnyCalculator>) {
+        self.helper().calculator = calculator;
+    }
+
+    /// Reads a line, and the following ones while a line ends with a backslash. `None` at the end of the input,
+    /// Ctrl-C discards the statement being typed.
+    pub fn read_statement(&mut self) -> Option<String> {
+        let mut buffer = String::new();
+        let mut prompt = "> ";
+        loop {
+            // a continued line is a part of a statement, so it isn't validated alone
+            self.helper().continued = !buffer.is_empty();
+            match self.editor.readline(prompt) {
+                Ok(line) => {
+                    buffer.push_str(&line);
+                    if !line.trim_end().ends_with('\\') {
+                        break;
+                    }
+                    buffer.push('\n');
+                    prompt = "... ";
+                }
+                Err(ReadlineError::Interrupted) => {
+                    buffer.clear();
+                    prompt = "> ";
+                }
+                Err(_) if buffer.is_empty() => return None,
+                Err(_) => break,
+            }
+        }
+
+        let statement = String::from(buffer.trim());
+        if !statement.is_empty() {
+            self.remember(&statement);
+        }
+
+        return Some(statement);
+    }
+
+    fn helper(&mut self) -> &mut ShellHelper {
+        return self.editor.helper_mut().unwrap();
+    }
+
+    /// Adds the statement to the history file at once, so it is kept however the shell ends.
+    fn remember(&mut self, statement: &str) {
+        // the history file has one entry per line
+        self.editor.add_history_entry(statement.replace("\\\n", " "));
+        if let Some(path) = &self.history {
+            if let Some(directory) = path.parent() {
+                let _ = std::fs::create_dir_all(directory);
+            }
+            let _ = self.editor.save_history(path);
+        }
+    }
+}
+
+pub struct ShellHelper {
+    calculator: Box<dyn AnyCalculator>,
+    colors: bool,
+    /// Whether the line being typed continues the previous one
+    continued: bool,
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let (start, names) = complete(line, pos, &self.calculator.names());
+
+        return Result::Ok((start, names.into_iter().map(|it| Pair { display: it.clone(), replacement: it }).collect()));
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {
+    fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
+        if !self.colors || self.continued || is_command(line) {
+            return Cow::Borrowed(line);
+        }
+
+        // the backslash of a continued statement is not a part of the expression
+        let statement = line.trim_end().strip_suffix('\\').unwrap_or(line);
+
+        return Cow::Owned(colorize(line, pos, &self.calculator.spans(statement)));
+    }
+
+    /// Redrawn on every cursor move, to follow the matching parentheses.
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        return self.colors;
+    }
+}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+/// Byte index where the name before `pos` starts, and the `names` beginning with it.
+/// Digits in front of the name are a number, e.g. of an implicit product `2pi`.
+pub fn complete(line: &str, pos: usize, names: &[String]) -> (usize, Vec<String>) {
+    let word = line[..pos].char_indices().rev()
+        .take_while(|(_, it)| it.is_alphanumeric() || *it == '_')
+        .last()
+        .map_or(pos, |(index, _)| index);
+    let start = line[word..pos].find(|it: char| !it.is_ascii_digit()).map_or(pos, |it| word + it);
+    let prefix = &line[start..pos];
+
+    return (start, names.iter().filter(|it| it.starts_with(prefix)).cloned().collect());
+}
+
+/// `line` with ANSI colors by kind of token. The invalid token is underlined, the parenthesis at or before
+/// the cursor `pos` (byte index) and its match are bold.
+pub fn colorize(line: &str, pos: usize, spans: &[Span]) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let matching = matching_parentheses(&chars, line[..pos].chars().count(), spans);
+
+    let mut colored = String::new();
+    let mut next = 0;
+    for span in spans {
+        if span.start < next || span.end > chars.len() {
+            continue;
+        }
+        let style = match span.kind {
+            TokenKind::Invalid => "4;31",
+            _ if matching.contains(&span.start) => "1",
+            TokenKind::Literal => "36",
+            TokenKind::Variable => "32",
+            TokenKind::Constant => "35",
+            TokenKind::Function => "34",
+            TokenKind::Operator => "33",
+            TokenKind::Parenthesis | TokenKind::Comma => "",
+        };
+        colored.extend(&chars[next..span.start]);
+        let text: String = chars[span.start..span.end].iter().collect();
+        if style.is_empty() {
+            colored.push_str(&text);
+        } else {
+            colored.push_str(&format!("\x1b[{}m{}\x1b[0m", style, text));
+        }
+        next = span.end;
+    }
+    colored.extend(&chars[next..]);
+
+    return colored;
+}
+
+/// Char indexes of the parenthesis at or right before `cursor` and of its match, empty without a match.
+fn matching_parentheses(chars: &[char], cursor: usize, spans: &[Span]) -> Vec<usize> {
+    let parentheses: Vec<usize> = spans.iter()
+        .filter(|it| it.kind == TokenKind::Parenthesis)
+        .map(|it| it.start)
+        .collect();
+    let current = match parentheses.iter().position(|it| *it == cursor).or_else(|| {
+        parentheses.iter().position(|it| *it + 1 == cursor)
+    }) {
+        Some(it) => it,
+        None => return Vec::new(),
+    };
+
+    let forward = chars[parentheses[current]] == '(';
+    let mut depth = 0;
+    let mut index = current;
+    loop {
+        depth += if chars[parentheses[index]] == '(' { 1 } else { -1 };
+        if depth == 0 {
+            return vec![parentheses[current], parentheses[index]];
+        }
+        if forward && index + 1 < parentheses.len() {
+            index += 1;
+        } else if !forward && index > 0 {
+            index -= 1;
+        } else {
+            return Vec::new();
+        }
+    }
+}
+
+fn is_command(line: &str) -> bool {
+    return line.trim_start().starts_with(':') || COMMANDS.contains(&line.trim());
+}
+
+/// `expr_calc/history` in the user's data directory, e.g. `~/.local/share` on Linux.
+fn history_path() -> Option<PathBuf> {
+    return dirs::data_dir().map(|it| it.join("expr_calc").join("history"));
+}
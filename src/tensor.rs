@@ -0,0 +1,333 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::format::{Format, NumberFormat};
+
+/// Value of the linear algebra calculator. Vectors have no orientation:
+/// `matrix * vector` treats the vector as a column, `vector * matrix` as a row.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Tensor {
+    Scalar(f64),
+    Vector(Vec<f64>),
+    Matrix(Vec<Vec<f64>>),
+}
+
+impl Tensor {
+    /// Parses `2.5`, `[1, 2, 3]` or `[[1, 2], [3, 4]]`, `number` reads an element without its sign.
+    /// The error tells what is wrong with an element, `None` when the brackets don't form a vector or matrix.
+    pub fn parse(input: &str, number: &dyn Fn(&str) -> Result<f64, String>) -> Result<Tensor, Option<String>> {
+        let mut chars = input.chars().peekable();
+        let result = parse_item(&mut chars, 0, number)?;
+        skip_spaces(&mut chars);
+
+        if chars.next().is_some() {
+            return Result::Err(None);
+        }
+
+        return match result {
+            Item::Number(value) => Result::Ok(Tensor::Scalar(value)),
+            Item::List(items) => to_tensor(items).map_err(|_| None),
+        };
+    }
+
+    pub fn shape(&self) -> String {
+        return match self {
+            Tensor::Scalar(_) => "scalar".to_string(),
+            Tensor::Vector(v) => format!("vector[{}]", v.len()),
+            Tensor::Matrix(m) => format!("matrix[{}x{}]", m.len(), m[0].len()),
+        };
+    }
+
+    pub fn neg(&self) -> Tensor {
+        return self.map(|it| -it);
+    }
+
+    pub fn add(&self, other: &Tensor) -> Result<Tensor, String> {
+        return self.zip_with(other, |a, b| a + b);
+    }
+
+    pub fn sub(&self, other: &Tensor) -> Result<Tensor, String> {
+        return self.zip_with(other, |a, b| a - b);
+    }
+
+    pub fn mul_elements(&self, other: &Tensor) -> Result<Tensor, String> {
+        return self.zip_with(other, |a, b| a * b);
+    }
+
+    pub fn div_elements(&self, other: &Tensor) -> Result<Tensor, String> {
+        return self.zip_with(other, |a, b| a / b);
+    }
+
+    /// Matrix product; scalars scale the other operand.
+    pub fn mul(&self, other: &Tensor) -> Result<Tensor, String> {
+        return match (self, other) {
+            (Tensor::Scalar(_), _) | (_, Tensor::Scalar(_)) => self.mul_elements(other),
+            (Tensor::Matrix(a), Tensor::Matrix(b)) if a[0].len() == b.len() => {
+                let columns = transpose(b);
+                Result::Ok(Tensor::Matrix(a.iter().map(|row| columns.iter().map(|col| dot(row, col)).collect()).collect()))
+            }
+            (Tensor::Matrix(a), Tensor::Vector(v)) if a[0].len() == v.len() => {
+                Result::Ok(Tensor::Vector(a.iter().map(|row| dot(row, v)).collect()))
+            }
+            (Tensor::Vector(v), Tensor::Matrix(b)) if v.len() == b.len() => {
+                Result::Ok(Tensor::Vector(transpose(b).iter().map(|col| dot(v, col)).collect()))
+            }
+            _ => Result::Err(mismatch(self, other)),
+        };
+    }
+
+    pub fn transpose(&self) -> Tensor {
+        return match self {
+            Tensor::Matrix(m) => Tensor::Matrix(transpose(m)),
+            _ => self.clone(),
+        };
+    }
+
+    pub fn dot(&self, other: &Tensor) -> Result<Tensor, String> {
+        return match (self, other) {
+            (Tensor::Vector(a), Tensor::Vector(b)) if a.len() == b.len() => Result::Ok(Tensor::Scalar(dot(a, b))),
+            _ => Result::Err(mismatch(self, other)),
+        };
+    }
+
+    pub fn cross(&self, other: &Tensor) -> Result<Tensor, String> {
+        return match (self, other) {
+            (Tensor::Vector(a), Tensor::Vector(b)) if a.len() == 3 && b.len() == 3 => Result::Ok(Tensor::Vector(vec![
+                a[1] * b[2] - a[2] * b[1],
+                a[2] * b[0] - a[0] * b[2],
+                a[0] * b[1] - a[1] * b[0],
+            ])),
+            _ => Result::Err(format!("cross product needs two vector[3], got {} and {}", self.shape(), other.shape())),
+        };
+    }
+
+    /// Euclidean norm of a vector, Frobenius norm of a matrix.
+    pub fn norm(&self) -> Tensor {
+        return match self {
+            Tensor::Scalar(value) => Tensor::Scalar(value.abs()),
+            Tensor::Vector(v) => Tensor::Scalar(dot(v, v).sqrt()),
+            Tensor::Matrix(m) => Tensor::Scalar(m.iter().map(|row| dot(row, row)).sum::<f64>().sqrt()),
+        };
+    }
+
+    pub fn det(&self) -> Result<Tensor, String> {
+        let mut m = self.square()?;
+        let size = m.len();
+        let mut result = 1.0;
+
+        for col in 0..size {
+            let pivot = (col..size).max_by(|a, b| m[*a][col].abs().total_cmp(&m[*b][col].abs())).unwrap();
+            if m[pivot][col] == 0.0 {
+                return Result::Ok(Tensor::Scalar(0.0));
+            }
+            if pivot != col {
+                m.swap(pivot, col);
+                result = -result;
+            }
+            result *= m[col][col];
+            for row in col + 1..size {
+                let factor = m[row][col] / m[col][col];
+                for k in col..size {
+                    m[row][k] -= factor * m[col][k];
+                }
+            }
+        }
+
+        return Result::Ok(Tensor::Scalar(result));
+    }
+
+    pub fn inv(&self) -> Result<Tensor, String> {
+        let mut m = self.square()?;
+        let size = m.len();
+        let mut result: Vec<Vec<f64>> = (0..size).map(|row| (0..size).map(|col| if row == col { 1.0 } else { 0.0 }).collect()).collect();
+
+        for col in 0..size {
+            let pivot = (col..size).max_by(|a, b| m[*a][col].abs().total_cmp(&m[*b][col].abs())).unwrap();
+            if m[pivot][col] == 0.0 {
+                return Result::Err("matrix is singular".to_string());
+            }
+            m.swap(pivot, col);
+            result.swap(pivot, col);
+
+            let divisor = m[col][col];
+            for k in 0..size {
+                m[col][k] /= divisor;
+                result[col][k] /= divisor;
+            }
+            for row in 0..size {
+                if row == col {
+                    continue;
+                }
+                let factor = m[row][col];
+                for k in 0..size {
+                    m[row][k] -= factor * m[col][k];
+                    result[row][k] -= factor * result[col][k];
+                }
+            }
+        }
+
+        return Result::Ok(Tensor::Matrix(result));
+    }
+
+    /// Rows of a square matrix, a scalar is a matrix[1x1]. Elimination has no pivot for NaN or infinite elements.
+    fn square(&self) -> Result<Vec<Vec<f64>>, String> {
+        let m = match self {
+            Tensor::Scalar(value) => vec![vec![*value]],
+            Tensor::Matrix(m) if m.len() == m[0].len() => m.clone(),
+            _ => return Result::Err(format!("square matrix expected, got {}", self.shape())),
+        };
+        if m.iter().flatten().any(|it| it.is_nan()) {
+            return Result::Err("matrix contains NaN".to_string());
+        }
+        if m.iter().flatten().any(|it| it.is_infinite()) {
+            return Result::Err("matrix contains an infinite element".to_string());
+        }
+
+        return Result::Ok(m);
+    }
+
+    fn map(&self, f: impl Fn(f64) -> f64) -> Tensor {
+        return match self {
+            Tensor::Scalar(value) => Tensor::Scalar(f(*value)),
+            Tensor::Vector(v) => Tensor::Vector(v.iter().map(|it| f(*it)).collect()),
+            Tensor::Matrix(m) => Tensor::Matrix(m.iter().map(|row| row.iter().map(|it| f(*it)).collect()).collect()),
+        };
+    }
+
+    /// Element-wise operation on equally shaped values, a scalar operand is applied to every element.
+    fn zip_with(&self, other: &Tensor, f: impl Fn(f64, f64) -> f64) -> Result<Tensor, String> {
+        return match (self, other) {
+            (Tensor::Scalar(a), _) => Result::Ok(other.map(|b| f(*a, b))),
+            (_, Tensor::Scalar(b)) => Result::Ok(self.map(|a| f(a, *b))),
+            (Tensor::Vector(a), Tensor::Vector(b)) if a.len() == b.len() => {
+                Result::Ok(Tensor::Vector(a.iter().zip(b).map(|(x, y)| f(*x, *y)).collect()))
+            }
+            (Tensor::Matrix(a), Tensor::Matrix(b)) if a.len() == b.len() && a[0].len() == b[0].len() => {
+                Result::Ok(Tensor::Matrix(a.iter().zip(b).map(|(r1, r2)| r1.iter().zip(r2).map(|(x, y)| f(*x, *y)).collect()).collect()))
+            }
+            _ => Result::Err(mismatch(self, other)),
+        };
+    }
+}
+
+impl Display for Tensor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        return match self {
+            Tensor::Scalar(value) => write!(f, "{}", value),
+            Tensor::Vector(v) => write!(f, "{}", format_row(v, |it| it.to_string())),
+            Tensor::Matrix(m) => write!(f, "[{}]", m.iter().map(|row| format_row(row, |it| it.to_string())).collect::<Vec<String>>().join(", ")),
+        };
+    }
+}
+
+impl Format for Tensor {
+    fn format(&self, format: &NumberFormat) -> String {
+        return match self {
+            Tensor::Scalar(value) => value.format(format),
+            Tensor::Vector(v) => format_row(v, |it| it.format(format)),
+            Tensor::Matrix(m) => format!("[{}]", m.iter().map(|row| format_row(row, |it| it.format(format))).collect::<Vec<String>>().join(", ")),
+        };
+    }
+
+    fn type_name(&self) -> &'static str {
+        return match self {
+            Tensor::Scalar(_) => "number",
+            Tensor::Vector(_) => "vector",
+            Tensor::Matrix(_) => "matrix",
+        };
+    }
+
+    /// Infinite and NaN elements of vectors and matrices are printed but can't be read back.
+    fn to_input(&self) -> String {
+        return match self {
+            Tensor::Scalar(value) => value.to_input(),
+            Tensor::Vector(v) => format_row(v, |it| format!("{:e}", it)),
+            Tensor::Matrix(m) => format!("[{}]", m.iter().map(|row| format_row(row, |it| format!("{:e}", it))).collect::<Vec<String>>().join(", ")),
+        };
+    }
+}
+
+enum Item {
+    Number(f64),
+    List(Vec<Item>),
+}
+
+fn parse_item(chars: &mut Peekable<Chars>, depth: usize, number: &dyn Fn(&str) -> Result<f64, String>) -> Result<Item, Option<String>> {
+    skip_spaces(chars);
+
+    if chars.peek() != Some(&'[') {
+        let negative = chars.peek() == Some(&'-');
+        if negative || chars.peek() == Some(&'+') {
+            chars.next();
+        }
+        let mut text = String::new();
+        while chars.peek().map_or(false, |it| !it.is_whitespace() && !",[]".contains(*it)) {
+            text.push(chars.next().unwrap());
+        }
+        let value = number(&text).map_err(|message| Some(format!("{} in '{}'", message, text)))?;
+        return Result::Ok(Item::Number(if negative { -value } else { value }));
+    }
+
+    if depth == 2 {
+        return Result::Err(None);
+    }
+    chars.next();
+
+    let mut items = Vec::new();
+    loop {
+        items.push(parse_item(chars, depth + 1, number)?);
+        skip_spaces(chars);
+        match chars.next() {
+            Some(',') => continue,
+            Some(']') => return Result::Ok(Item::List(items)),
+            _ => return Result::Err(None),
+        }
+    }
+}
+
+fn to_tensor(items: Vec<Item>) -> Result<Tensor, ()> {
+    let mut numbers = Vec::new();
+    let mut rows = Vec::new();
+
+    for item in items {
+        match item {
+            Item::Number(value) => numbers.push(value),
+            Item::List(row) => rows.push(row.into_iter().map(|it| match it {
+                Item::Number(value) => Result::Ok(value),
+                Item::List(_) => Result::Err(()),
+            }).collect::<Result<Vec<f64>, ()>>()?),
+        }
+    }
+
+    if rows.is_empty() {
+        return Result::Ok(Tensor::Vector(numbers));
+    }
+    if !numbers.is_empty() || rows.iter().any(|row| row.len() != rows[0].len()) {
+        return Result::Err(());
+    }
+
+    return Result::Ok(Tensor::Matrix(rows));
+}
+
+fn skip_spaces(chars: &mut Peekable<Chars>) {
+    while chars.peek().map_or(false, |it| it.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn format_row<F: Fn(&f64) -> String>(row: &Vec<f64>, number: F) -> String {
+    return format!("[{}]", row.iter().map(number).collect::<Vec<String>>().join(", "));
+}
+
+fn dot(a: &Vec<f64>, b: &Vec<f64>) -> f64 {
+    return a.iter().zip(b).map(|(x, y)| x * y).sum();
+}
+
+fn transpose(m: &Vec<Vec<f64>>) -> Vec<Vec<f64>> {
+    return (0..m[0].len()).map(|col| m.iter().map(|row| row[col]).collect()).collect();
+}
+
+fn mismatch(a: &Tensor, b: &Tensor) -> String {
+    return format!("shape mismatch: {} and {}", a.shape(), b.shape());
+}
@@ -1,6 +1,29 @@
 #[cfg(test)]
 mod tests {
-    use crate::ast::{ExprCalculator, HIGH_ORDER, LOW_ORDER, PrimitiveHandler};
+    use crate::{apply_setting, carry_over, exit_status, operation_help, save_session, saved_mode, split_command, Command, Opts, EVALUATION_ERROR, SYNTAX_ERROR};
+    use crate::angle::AngleUnit;
+    use crate::ast::{ExprCalculator, HIGH_ORDER, LOW_ORDER, PrimitiveHandler, TokenKind, Variables};
+    use crate::bool_calculator::boolean_calculator;
+    use crate::boolean_forms::{cnf, counterexample, dnf, minimize};
+    use crate::decimal::Rounding;
+    use crate::decimal_calculator::decimal_calculator;
+    use crate::engine::compare;
+    use crate::f64_calculator::f64_calculator_with;
+    use crate::float_calculator::float_calculator_with;
+    use crate::format::{Format, NumberFormat};
+    use crate::matrix_calculator::matrix_calculator;
+    use crate::mixed_calculator::mixed_calculator_with;
+    use crate::output::{json_error, json_outcome};
+    use crate::rational_calculator::rational_calculator;
+    use crate::registry::{bool_mode, f64_mode, find, float_mode, modes, Outcome, Settings};
+    use crate::script::statements;
+    use crate::shell::{colorize, complete};
+    use crate::tensor::Tensor;
+    use crate::truth_table::truth_table;
+    use crate::value::Value;
+    use clap::Clap;
+    use rug::Float;
+    use rug::float::Constant;
 
     struct IntHandler {}
 
@@ -137,4 +160,728 @@ mod tests {
         assert_eq!(token.get_pos(), 3);
         assert_eq!(token.get_value(), ")".to_string());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn it_calculates_decimal_exactly() {
+        let calculator = decimal_calculator(2, Rounding::HalfEven);
+
+        assert_eq!(calculator.calculate("0.1+0.2").ok().unwrap().to_string(), "0.3");
+        assert_eq!(calculator.calculate("19.99*3").ok().unwrap().to_string(), "59.97");
+        assert_eq!(calculator.calculate("1234567.891*3").ok().unwrap().round(2, Rounding::HalfEven).to_grouped_string(), "3,703,703.67");
+    }
+
+    #[test]
+    fn it_rounds_decimal_division() {
+        let half_even = decimal_calculator(2, Rounding::HalfEven);
+        let half_up = decimal_calculator(2, Rounding::HalfUp);
+
+        assert_eq!(half_even.calculate("1/8").ok().unwrap().to_string(), "0.12");
+        assert_eq!(half_up.calculate("1/8").ok().unwrap().to_string(), "0.13");
+        assert_eq!(half_even.calculate("-2/3").ok().unwrap().to_string(), "-0.67");
+    }
+
+    #[test]
+    fn it_fails_on_decimal_division_by_zero() {
+        let calculator = decimal_calculator(2, Rounding::HalfEven);
+
+        let result = calculator.calculate("1+2/0");
+        assert_eq!(result.is_err(), true);
+
+        let token = result.err().unwrap();
+        assert_eq!(token.is_failure(), true);
+        assert_eq!(token.get_pos(), 3);
+        assert_eq!(token.get_value(), "/".to_string());
+    }
+
+    #[test]
+    fn it_bounds_decimal_powers() {
+        let calculator = decimal_calculator(2, Rounding::HalfEven);
+
+        assert_eq!(calculator.calculate("1.5^7").ok().unwrap().to_string(), "17.0859375");
+        assert_eq!(calculator.calculate("2^-3").ok().unwrap().to_string(), "0.12");
+        assert_eq!(calculator.calculate("2^4000000000").err().unwrap().is_failure(), true);
+        assert_eq!(calculator.calculate("0.1^5000000").err().unwrap().is_failure(), true);
+    }
+
+    #[test]
+    fn it_calculates_matrices() {
+        let calculator = matrix_calculator();
+
+        assert_eq!(calculator.calculate("[[1,2],[3,4]] * [1, 1]").ok().unwrap(), Tensor::Vector(vec![3.0, 7.0]));
+        assert_eq!(calculator.calculate("dot([1, 2], [3, 4]) + det([[2, 0], [0, 2]])").ok().unwrap(), Tensor::Scalar(15.0));
+        assert_eq!(calculator.calculate("cross([1,0,0], [0,1,0])").ok().unwrap(), Tensor::Vector(vec![0.0, 0.0, 1.0]));
+        assert_eq!(calculator.calculate("det([[0,0],[0,0]]/0)").err().unwrap().is_failure(), true);
+        assert_eq!(calculator.calculate("inv([[1,0],[0,1]]/0)").err().unwrap().is_failure(), true);
+    }
+
+    #[test]
+    fn it_fails_on_shape_mismatch() {
+        let calculator = matrix_calculator();
+
+        let token = calculator.calculate("[1, 2] + [1, 2, 3]").err().unwrap();
+        assert_eq!(token.is_failure(), true);
+        assert_eq!(token.get_pos(), 7);
+        assert_eq!(token.get_value(), "+".to_string());
+
+        let token = calculator.calculate("dot([1, 2])").err().unwrap();
+        assert_eq!(token.is_failure(), false);
+        assert_eq!(token.get_value(), "dot".to_string());
+    }
+
+    #[test]
+    fn it_substitutes_variables() {
+        let mut calculator = ExprCalculator::<i32>::new(Box::new(IntHandler {}));
+        calculator.add_infix(
+            "+".to_string(),
+            "+".to_string(),
+            Box::new(|op1, op2| { op1 + op2 }),
+            LOW_ORDER,
+        );
+
+        let mut variables = Variables::new();
+        variables.insert("x".to_string(), 2);
+        variables.insert("long_name1".to_string(), 40);
+
+        let expression = calculator.compile("x + long_name1").ok().unwrap();
+        assert_eq!(expression.variables(), vec!["x".to_string(), "long_name1".to_string()]);
+        assert_eq!(expression.calculate(&variables).ok().unwrap(), 42);
+
+        let token = calculator.calculate("x + 1").err().unwrap();
+        assert_eq!(token.get_pos(), 0);
+        assert_eq!(token.get_value(), "x".to_string());
+
+        let token = calculator.calculate("x 1").err().unwrap();
+        assert_eq!(token.get_pos(), 2);
+    }
+
+    #[test]
+    fn it_builds_truth_table() {
+        let table = truth_table(&boolean_calculator(), "b ^ a").ok().unwrap();
+
+        assert_eq!(table.variables, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(table.minterms(), vec![1, 2]);
+        assert_eq!(table.to_csv(), "a,b,\"b ^ a\"\n0,0,0\n0,1,1\n1,0,1\n1,1,0");
+    }
+
+    #[test]
+    fn it_converts_to_normal_forms() {
+        let table = truth_table(&boolean_calculator(), "(a & b) | (!a & b) | (a & !b)").ok().unwrap();
+
+        assert_eq!(dnf(&table), "(!a & b) | (a & !b) | (a & b)");
+        assert_eq!(cnf(&table), "a | b");
+        assert_eq!(minimize(&table).ok().unwrap(), "a | b");
+    }
+
+    #[test]
+    fn it_minimizes_without_essential_implicants() {
+        let calculator = boolean_calculator();
+
+        let cyclic = "(!a & !b & !c) | (!a & !b & c) | (!a & b & !c) | (a & !b & c) | (a & b & !c) | (a & b & c)";
+        let minimal = minimize(&truth_table(&calculator, cyclic).ok().unwrap()).ok().unwrap();
+        assert_eq!(minimal.matches(" | ").count(), 2);
+        assert_eq!(counterexample(&calculator, cyclic, &minimal).ok().unwrap(), None);
+
+        // 90 prime implicants `x & !y`, each minterm is covered by several of them
+        let not_all_equal = "(a | b | c | d | e | f | g | h | i | j) & !(a & b & c & d & e & f & g & h & i & j)";
+        let minimal = minimize(&truth_table(&calculator, not_all_equal).ok().unwrap()).ok().unwrap();
+        assert_eq!(counterexample(&calculator, not_all_equal, &minimal).ok().unwrap(), None);
+    }
+
+    #[test]
+    fn it_checks_equivalence() {
+        let calculator = boolean_calculator();
+
+        assert_eq!(counterexample(&calculator, "!(a & b)", "!a | !b").ok().unwrap(), None);
+        assert_eq!(
+            counterexample(&calculator, "a ^ b", "a | c").ok().unwrap(),
+            Some("a=0, b=0, c=1 (left=0, right=1)".to_string())
+        );
+    }
+
+    #[test]
+    fn it_groups_equal_priority_from_left() {
+        let mut calculator = ExprCalculator::<i32>::new(Box::new(IntHandler {}));
+        calculator.add_infix(
+            "-".to_string(),
+            "-".to_string(),
+            Box::new(|op1, op2| { op1 - op2 }),
+            LOW_ORDER,
+        );
+        calculator.add_right_infix(
+            "^".to_string(),
+            "^".to_string(),
+            Box::new(|op1, op2| { op1.pow(op2 as u32) }),
+            HIGH_ORDER,
+        );
+
+        assert_eq!(calculator.calculate("10-3-2").ok().unwrap(), 5);
+        assert_eq!(calculator.calculate("2^3^2").ok().unwrap(), 512);
+    }
+
+    #[test]
+    fn it_groups_operators_of_every_calculator() {
+        let decimal = decimal_calculator(2, Rounding::HalfEven);
+        assert_eq!(decimal.calculate("8 - 2 - 1").ok().unwrap().to_string(), "5");
+        assert_eq!(decimal.calculate("16 / 4 / 2").ok().unwrap().to_string(), "2.00");
+        assert_eq!(decimal.calculate("2^3^2").ok().unwrap().to_string(), "512");
+        assert_eq!(matrix_calculator().calculate("8 - 2 - 1").ok().unwrap(), Tensor::Scalar(5.0));
+
+        let calculator = boolean_calculator();
+        assert_eq!(counterexample(&calculator, "a -> b -> c", "a -> (b -> c)").ok().unwrap(), None);
+        assert!(counterexample(&calculator, "a -> b -> c", "(a -> b) -> c").ok().unwrap().is_some());
+    }
+
+    #[test]
+    fn it_groups_operators_of_all_modes() {
+        for name in ["float", "f64", "rational", "decimal", "mixed"].iter() {
+            let calculator = find(name).ok().unwrap().create(&Settings::default());
+            let value = |input: &str| -> f64 { calculator.calculate(input).ok().unwrap().parse().unwrap() };
+            assert_eq!(value("8 - 2 - 1"), 5.0, "{}", name);
+            assert_eq!(value("16 / 4 / 2"), 2.0, "{}", name);
+            assert_eq!(value("2^3^2"), 512.0, "{}", name);
+        }
+        let calculator = find("bool").ok().unwrap().create(&Settings::default());
+        assert_eq!(calculator.calculate("false -> true -> false").ok().unwrap(), "true");
+    }
+
+    #[test]
+    fn it_respects_boolean_precedence() {
+        let calculator = boolean_calculator();
+
+        assert_eq!(calculator.calculate("true | false & false").ok().unwrap(), true);
+        assert_eq!(calculator.calculate("1 ^ 1 & 0").ok().unwrap(), true);
+        assert_eq!(calculator.calculate("0 -> 1 -> 0").ok().unwrap(), true);
+        assert_eq!(calculator.calculate("not 1 or 0 <-> false").ok().unwrap(), true);
+        assert_eq!(calculator.calculate("1 nand 1 nor 0").ok().unwrap(), true);
+    }
+
+    #[test]
+    fn it_skips_operands_of_lazy_operations() {
+        let mut calculator = ExprCalculator::<i32>::new(Box::new(IntHandler {}));
+        calculator.add_lazy_infix(
+            "&&".to_string(),
+            "&&".to_string(),
+            Box::new(|operands| { if operands[0].get()? == 0 { Result::Ok(0) } else { operands[1].get() } }),
+            LOW_ORDER,
+        );
+        calculator.add_function(
+            "fail".to_string(),
+            "fail".to_string(),
+            Box::new(|_| { Result::Err("failed".to_string()) }),
+            1,
+            HIGH_ORDER,
+        );
+
+        assert_eq!(calculator.calculate("0 && fail(1)").ok().unwrap(), 0);
+
+        let token = calculator.calculate("1 && fail(1)").err().unwrap();
+        assert_eq!(token.is_failure(), true);
+        assert_eq!(token.get_pos(), 5);
+        assert_eq!(token.get_value(), "fail".to_string());
+
+        let token = calculator.calculate("0 && x").err().unwrap();
+        assert_eq!(token.get_value(), "x".to_string());
+    }
+
+    #[test]
+    fn it_calculates_boolean_if() {
+        let calculator = boolean_calculator();
+
+        assert_eq!(calculator.calculate("if(1, false, true)").ok().unwrap(), false);
+        assert_eq!(calculator.calculate("if(0 | 0, false, !false)").ok().unwrap(), true);
+    }
+
+    #[test]
+    fn it_mixes_numbers_and_booleans() {
+        let calculator = mixed_calculator_with(64, AngleUnit::Rad);
+
+        let mut variables = Variables::new();
+        variables.insert("x".to_string(), Value::Number(Float::with_val(64, 5)));
+        variables.insert("y".to_string(), Value::Number(Float::with_val(64, 2)));
+
+        assert_eq!(calculator.calculate_with("x > 3 & y <= 2", &variables).ok().unwrap(), Value::Boolean(true));
+        assert_eq!(calculator.calculate_with("min(x, y) < 0 | x != y", &variables).ok().unwrap(), Value::Boolean(true));
+        assert_eq!(calculator.calculate_with("if(x == 5, y * 10, 0)", &variables).ok().unwrap(), Value::Number(Float::with_val(64, 20)));
+
+        let token = calculator.calculate("true + 1").err().unwrap();
+        assert_eq!(token.is_failure(), true);
+        assert_eq!(token.get_pos(), 5);
+        assert_eq!(token.to_string(), "'+' at position 5: type error: expected number, got boolean");
+        assert_eq!(calculator.calculate("true < 1").err().unwrap().to_string(), "'<' at position 5: type error: expected number, got boolean");
+        assert_eq!(calculator.calculate("true == 1").err().unwrap().to_string(), "'==' at position 5: type error: can't compare boolean with number");
+
+        let comparisons = |name: &str| -> Vec<(String, String, u8)> {
+            find(name).ok().unwrap().create(&Settings::default()).operations().into_iter()
+                .filter(|it| ["<", "<=", ">", ">=", "==", "!="].contains(&it.spellings[0].as_str()))
+                .map(|it| (it.spellings[0].clone(), it.description, it.priority))
+                .collect()
+        };
+        let mixed = comparisons("mixed");
+        assert_eq!(mixed.len(), 6);
+        assert_eq!(mixed[0].1, "true if x is less than y, otherwise false");
+        for name in ["float", "f64", "rational"].iter() {
+            let numeric = comparisons(name);
+            assert_eq!(numeric[0].1, "1 if x is less than y, otherwise 0");
+            assert_eq!(
+                numeric.iter().map(|(spelling, _, priority)| (spelling, priority)).collect::<Vec<_>>(),
+                mixed.iter().map(|(spelling, _, priority)| (spelling, priority)).collect::<Vec<_>>(),
+                "{}", name
+            );
+        }
+    }
+
+    #[test]
+    fn it_calculates_conditionals() {
+        let calculator = float_calculator_with(64, AngleUnit::Rad);
+
+        assert_eq!(calculator.calculate("120 > 100 ? 120 * 0.5 : 120").ok().unwrap(), Float::with_val(64, 60));
+        assert_eq!(calculator.calculate("0 ? 1 : 1 ? 0 ? 2 : 3 : 4").ok().unwrap(), Float::with_val(64, 3));
+        assert_eq!(calculator.calculate("if(2 >= 2, 10, ln(0 - 1))").ok().unwrap(), Float::with_val(64, 10));
+        assert_eq!(calculator.calculate("piecewise(50 > 100, 1, 50 > 10, 2, 3)").ok().unwrap(), Float::with_val(64, 2));
+        assert_eq!(calculator.calculate("piecewise(0, 1, 0, 2, 3)").ok().unwrap(), Float::with_val(64, 3));
+
+        let token = calculator.calculate("1 ? 2").err().unwrap();
+        assert_eq!(token.get_value(), "?".to_string());
+
+        let token = calculator.calculate("1 ? 2 : 3 : 4").err().unwrap();
+        assert_eq!(token.get_pos(), 10);
+
+        let token = calculator.calculate("piecewise(1, 2)").err().unwrap();
+        assert_eq!(token.get_value(), "piecewise".to_string());
+
+        let pairs = vec!["0, 1"; 128].join(", ");
+        assert_eq!(calculator.calculate(&format!("piecewise({}, 7)", pairs)).ok().unwrap(), Float::with_val(64, 7));
+        let token = calculator.calculate(&format!("if({}, 1)", vec!["1"; 258].join(", "))).err().unwrap();
+        assert_eq!(token.get_value(), "if".to_string());
+    }
+
+    #[test]
+    fn it_calculates_rationals_exactly() {
+        let calculator = rational_calculator();
+
+        assert_eq!(calculator.calculate("1/3 + 0.1 * 2^-2").ok().unwrap().to_string(), "43/120");
+        assert_eq!(calculator.calculate("1/3 * 3 == 1").ok().unwrap().to_string(), "1");
+        assert_eq!(calculator.calculate("(-1)^2000000001 + 1^2000000000").ok().unwrap().to_string(), "0");
+        assert_eq!(calculator.calculate("2^2000000000").err().unwrap().to_string(), "'^' at position 1: result is too large");
+
+        let token = calculator.calculate("1/(2-2)").err().unwrap();
+        assert_eq!(token.to_string(), "'/' at position 1: division by zero");
+    }
+
+    #[test]
+    fn it_compares_engines() {
+        let fast = f64_calculator_with(AngleUnit::Rad).calculate("sqrt 2 * 5! > 169 ? 1 : 0").ok().unwrap();
+        let precise = float_calculator_with(64, AngleUnit::Rad).calculate("sqrt 2 * 5! > 169 ? 1 : 0").ok().unwrap();
+        assert_eq!(compare(&precise, fast, 1e-12), (0.0, true));
+
+        assert_eq!(compare(&Float::with_val(64, 1000), 1000.5, 1e-3), (0.5, true));
+        assert_eq!(compare(&Float::with_val(64, 1000), 1002.0, 1e-3), (2.0, false));
+
+        let settings = Settings { si_suffixes: true, implicit_multiplication: true, ..Settings::default() };
+        assert_eq!(float_mode(&settings).calculate("3 sqrt(4) + 4.7k").ok().unwrap(), float_mode(&Settings::default()).calculate("3 * sqrt(4) + 4700").ok().unwrap());
+        assert_eq!(f64_mode(&settings).calculate("2k").ok().unwrap(), 2000.0);
+        assert!(f64_mode(&Settings::default()).calculate("2k").is_err());
+        assert_eq!(truth_table(&bool_mode(&settings), "a & b").ok().unwrap().to_csv(), truth_table(&boolean_calculator(), "a & b").ok().unwrap().to_csv());
+    }
+
+    #[test]
+    fn it_finds_modes_by_name() {
+        let settings = Settings::default();
+
+        assert_eq!(find("rational").ok().unwrap().create(&settings).calculate("1/3 + 1/6").ok().unwrap(), "1/2");
+        assert_eq!(find("decimal").ok().unwrap().create(&settings).calculate("10/3").ok().unwrap(), "3.33");
+        assert_eq!(modes().iter().all(|it| find(it.name).is_ok()), true);
+        assert_eq!(find("unknown").err().unwrap().starts_with("unknown mode 'unknown'"), true);
+
+        let diagnostic = find("bool").ok().unwrap().create(&settings).calculate("true &").err().unwrap();
+        assert_eq!(diagnostic.pos, 5);
+        assert_eq!(diagnostic.is_failure(), false);
+    }
+
+    #[test]
+    fn it_multiplies_implicitly() {
+        let mut calculator = float_calculator_with(64, AngleUnit::Rad);
+        assert_eq!(calculator.calculate("2pi").err().unwrap().get_value(), "pi".to_string());
+
+        calculator.enable_implicit_multiplication("*");
+        assert_eq!(calculator.calculate("4(1+2)").ok().unwrap(), Float::with_val(64, 12));
+        assert_eq!(calculator.calculate("(1+1)(2+3)").ok().unwrap(), Float::with_val(64, 10));
+        assert_eq!(calculator.calculate("2sqrt 4 + 3cos(0)").ok().unwrap(), Float::with_val(64, 7));
+        assert_eq!(calculator.calculate("2e3").ok().unwrap(), Float::with_val(64, 2000));
+        assert_eq!(calculator.calculate("1/2pi").ok().unwrap(), Float::with_val(64, Constant::Pi) / 2);
+
+        let mut variables = Variables::new();
+        variables.insert("x".to_string(), Float::with_val(64, 5));
+        assert_eq!(calculator.calculate_with("3x", &variables).ok().unwrap(), Float::with_val(64, 15));
+
+        let token = calculator.calculate("(1)2").err().unwrap();
+        assert_eq!(token.get_pos(), 3);
+    }
+
+    #[test]
+    fn it_accepts_aliases() {
+        let calculator = float_calculator_with(64, AngleUnit::Rad);
+
+        assert_eq!(calculator.calculate("3 × 4 ÷ 2 − −1").ok().unwrap(), Float::with_val(64, 7));
+        assert_eq!(calculator.calculate("√16 + 2² + 2³ + (1 ≤ 2)").ok().unwrap(), Float::with_val(64, 17));
+        assert_eq!(boolean_calculator().calculate("¬true ∨ true ∧ true").ok().unwrap(), true);
+        assert_eq!(f64_calculator_with(AngleUnit::Rad).calculate("√16 × 3 ÷ 2 − −1 + (1 ≤ 2)").ok().unwrap(), 8.0);
+        let mixed = mixed_calculator_with(64, AngleUnit::Rad);
+        assert_eq!(mixed.calculate("√16 × 3 ÷ 2 − 6 ≠ 0 ∨ ¬(π ≥ 3)").ok().unwrap(), Value::Boolean(false));
+
+        let token = calculator.calculate("2 × × 3").err().unwrap();
+        assert_eq!(token.get_pos(), 4);
+        assert_eq!(token.get_value(), "×".to_string());
+    }
+
+    #[test]
+    fn it_reads_number_literals() {
+        let mut calculator = float_calculator_with(64, AngleUnit::Rad);
+
+        assert_eq!(calculator.calculate("0x1F + 0b1010 + 0o17").ok().unwrap(), Float::with_val(64, 56));
+        assert_eq!(calculator.calculate("1_000_000 + .5 * 2").ok().unwrap(), Float::with_val(64, 1000001));
+        assert_eq!(calculator.calculate("1.5e+3 - 25E-1").ok().unwrap(), Float::with_val(64, 1497.5));
+        assert_eq!(calculator.calculate("4.7k").err().unwrap().get_value(), "k".to_string());
+
+        let token = calculator.calculate("1 + 1.2.3").err().unwrap();
+        assert_eq!(token.get_pos(), 4);
+        assert_eq!(token.to_string(), "'1.2.3' at position 4: more than one decimal point");
+        assert_eq!(calculator.calculate("0b102").err().unwrap().to_string(), "'0b102' at position 0: invalid digit '2' in binary number");
+        assert_eq!(calculator.calculate("1__0").err().unwrap().is_failure(), false);
+
+        calculator.enable_si_suffixes();
+        assert_eq!(calculator.calculate("4.7k + 10M").ok().unwrap(), Float::with_val(64, 10004700));
+        assert_eq!(rational_calculator().calculate("1.5e-3 + 0x10").ok().unwrap().to_string(), "32003/2000");
+
+        let mut rational = rational_calculator();
+        rational.enable_si_suffixes();
+        assert_eq!(rational.calculate("3u").ok().unwrap().to_string(), "3/1000000");
+        assert_eq!(decimal_calculator(2, Rounding::HalfEven).calculate("1.5e3 + 0x10").ok().unwrap().to_string(), "1516");
+
+        let mut matrix = matrix_calculator();
+        assert_eq!(matrix.calculate(".5 + 1_000").ok().unwrap(), Tensor::Scalar(1000.5));
+        assert_eq!(matrix.calculate("[0x1F, -.5, 1_0e-1] + 0b1").ok().unwrap(), Tensor::Vector(vec![32.0, 0.5, 2.0]));
+        assert_eq!(matrix.calculate("1.2.3").err().unwrap().to_string(), "'1.2.3' at position 0: more than one decimal point");
+        assert_eq!(
+            matrix.calculate("[[1, 2], [3, 1.2.3]]").err().unwrap().to_string(),
+            "'[[1, 2], [3, 1.2.3]]' at position 0: more than one decimal point in '1.2.3'"
+        );
+        assert!(matrix.calculate("[1, 4.7k]").is_err());
+        matrix.enable_si_suffixes();
+        assert_eq!(matrix.calculate("[1, 4.7k] * 2").ok().unwrap(), Tensor::Vector(vec![2.0, 9400.0]));
+
+        for input in ["1.5e-2147483647", "1e2147483647", "1e999999999", "1e100001"].iter() {
+            assert_eq!(rational.calculate(input).err().unwrap().to_string(), format!("'{}' at position 0: exponent is too large", input));
+            assert_eq!(decimal_calculator(2, Rounding::HalfEven).calculate(input).err().unwrap().is_failure(), false);
+        }
+    }
+
+    #[test]
+    fn it_formats_results() {
+        let auto = NumberFormat::default();
+        assert_eq!((-21.25).format(&auto), "-21.25");
+        assert_eq!(1e21.format(&auto), "1e21");
+        assert_eq!(0.0000001.format(&auto), "1e-7");
+        assert_eq!(0.5.format(&auto), "0.5");
+
+        let sci = auto.apply("sci digits 4").ok().unwrap();
+        assert_eq!(123456.789.format(&sci), "1.235e5");
+        assert_eq!(9.9999.format(&sci), "1e1");
+        assert_eq!(0.000123.format(&auto.apply("eng").ok().unwrap()), "123e-6");
+        assert_eq!(9.996.format(&auto.apply("sci decimals 2").ok().unwrap()), "1.00e1");
+
+        let fixed = auto.apply("fixed decimals 2 thousands on").ok().unwrap();
+        assert_eq!(1234567.891.format(&fixed), "1,234,567.89");
+        assert_eq!((-0.004).format(&fixed), "0.00");
+        assert_eq!(fixed.apply("decimals all").ok().unwrap().decimals, None);
+        assert_eq!(auto.apply("digits").err().unwrap(), "expected a number or 'all' after 'digits'");
+        assert_eq!(auto.apply("pretty").err().unwrap().starts_with("unknown format 'pretty'"), true);
+
+        assert_eq!(rational_calculator().calculate("1/4").ok().unwrap().format(&auto), "1/4");
+        assert_eq!(rational_calculator().calculate("1/4").ok().unwrap().format(&fixed), "0.25");
+        assert_eq!(decimal_calculator(2, Rounding::HalfEven).calculate("0.10 + 0.20").ok().unwrap().format(&auto), "0.30");
+    }
+
+    #[test]
+    fn it_prints_results_in_other_bases() {
+        let format = NumberFormat::default();
+        assert_eq!(255.0.format(&format.apply("hex").ok().unwrap()), "0xff");
+        assert_eq!((-255.0).format(&format.apply("base 36").ok().unwrap()), "-36#73");
+        assert_eq!(2.5.format(&format.apply("bin").ok().unwrap()), "0b10.1");
+        assert_eq!(0.1.format(&format.apply("hex").ok().unwrap()), "0x0.1999999999999a");
+        assert_eq!(format.apply("base 37").err().unwrap(), "expected a base from 2 to 36 after 'base'");
+        assert_eq!(
+            1.0.format(&format.apply("ieee on").ok().unwrap()),
+            "1\nbits 0x3FF0000000000000\nsign 0, exponent 01111111111 (2^0), mantissa 0000000000000000000000000000000000000000000000000000"
+        );
+
+        let rational = find("rational").ok().unwrap().create(&Settings::default());
+        assert_eq!(rational.calculate("1/4 + 1/2 to bin").ok().unwrap(), "0b0.11");
+        assert_eq!(rational.calculate("1/3 to oct digits 4").ok().unwrap(), "0o0.2525...");
+        assert_eq!(rational.calculate("1 + \\\n2 to hex").ok().unwrap(), "0x3");
+        assert_eq!(rational.calculate("1 + 2 \\\r\nto hex").ok().unwrap(), "0x3");
+        assert_eq!(rational.calculate("1 +\n2\nto hex").ok().unwrap(), "0x3");
+
+        let diagnostic = rational.calculate("1 to base 1").err().unwrap();
+        assert_eq!(diagnostic.pos, 5);
+        assert_eq!(diagnostic.value, "base 1");
+        assert!(find("float").ok().unwrap().create(&Settings::default()).calculate("1 to ieee on").is_err());
+
+        let mut boolean = find("bool").ok().unwrap().create(&Settings::default());
+        let diagnostic = boolean.calculate("true to hex").err().unwrap();
+        assert_eq!((diagnostic.pos, diagnostic.value.as_str()), (8, "hex"));
+        assert!(diagnostic.message.unwrap().contains("can't be converted"));
+        boolean.execute("to = true").ok().unwrap();
+        assert_eq!(boolean.calculate("false | to & true").ok().unwrap(), "true");
+        let matrix = find("matrix").ok().unwrap().create(&Settings::default());
+        assert_eq!(matrix.calculate("[10, 255] to hex").ok().unwrap(), "[0xa, 0xff]");
+    }
+
+    #[test]
+    fn it_accepts_any_whitespace() {
+        let calculator = float_calculator_with(64, AngleUnit::Rad);
+        assert_eq!(calculator.calculate("1\t+\u{2009}2\n*\r\n3").ok().unwrap(), Float::with_val(64, 7));
+        assert_eq!(calculator.calculate("1 + \\\n2 * \\\r\n3").ok().unwrap(), Float::with_val(64, 7));
+        assert_eq!(calculator.calculate("1 \\ 2").err().unwrap().get_value(), "\\".to_string());
+
+        let float = find("float").ok().unwrap().create(&Settings::default());
+        let diagnostic = float.calculate("1 +\n2 *\n\t3 +").err().unwrap();
+        assert_eq!((diagnostic.pos, diagnostic.line, diagnostic.column), (11, 3, 4));
+        assert_eq!(diagnostic.to_string(), "'+' at line 3, column 4");
+        assert_eq!(float.calculate("1 + +").err().unwrap().to_string(), "'+' at position 4");
+    }
+
+    #[test]
+    fn it_runs_scripts() {
+        let source = "# comment\nx = 2; y = x * \\\n  3 # six\n\ny + 1;x == 2";
+        let statements = statements(source);
+        let texts: Vec<&str> = statements.iter().map(|it| it.text.as_str()).collect();
+        assert_eq!(texts, vec!["x = 2", " y = x * \\\n  3 ", "y + 1", "x == 2"]);
+        assert_eq!(statements[1].offset, 16);
+
+        let mut calculator = find("float").ok().unwrap().create(&Settings::default());
+        let values: Vec<String> = statements.iter().map(|it| match calculator.execute(&it.text).ok().unwrap() {
+            Outcome::Value { value, .. } => value,
+            Outcome::Assignment { name, value, .. } => format!("{} = {}", name, value),
+        }).collect();
+        assert_eq!(values, vec!["x = 2", "y = 6", "7", "1"]);
+
+        let diagnostic = calculator.execute("z = 1 + + 2").err().unwrap();
+        assert_eq!((diagnostic.pos, diagnostic.value.as_str()), (8, "+"));
+        assert_eq!(calculator.execute(" pi = 3").err().unwrap().describe(), "'pi': an operation can't be assigned");
+        assert_eq!(calculator.calculate("z").err().unwrap().pos, 0);
+    }
+
+    #[test]
+    fn it_prints_json() {
+        let mut calculator = find("mixed").ok().unwrap().create(&Settings::default());
+        let outcome = calculator.execute("x = 2 > 1").ok().unwrap();
+        assert_eq!(json_outcome("x = 2 > 1", &outcome), r#"{"input": "x = 2 > 1", "value": "true", "type": "boolean", "variable": "x"}"#);
+        let outcome = calculator.answer("1 / 4").ok().unwrap();
+        assert_eq!(json_outcome("1 / 4", &outcome), r#"{"input": "1 / 4", "value": "0.25", "type": "number"}"#);
+
+        let input = "\"a\"\t+ 0x";
+        let diagnostic = calculator.calculate(input).err().unwrap();
+        assert_eq!(json_error(input, diagnostic.pos, &diagnostic), concat!(
+            r#"{"input": "\"a\"\t+ 0x", "error": {"kind": "syntax", "message": "unexpected '\"'", "#,
+            r#""token": "\"", "start": 0, "end": 1, "line": 1, "column": 1}}"#
+        ));
+        let diagnostic = calculator.calculate("1 + 0x").err().unwrap();
+        assert_eq!(json_error("1 + 0x", diagnostic.pos, &diagnostic), concat!(
+            r#"{"input": "1 + 0x", "error": {"kind": "syntax", "message": "missing digits after '0x'", "#,
+            r#""token": "0x", "start": 4, "end": 6, "line": 1, "column": 5}}"#
+        ));
+        let diagnostic = find("rational").ok().unwrap().create(&Settings::default()).calculate("1 / 0").err().unwrap();
+        assert!(json_error("1 / 0", diagnostic.pos, &diagnostic).contains(r#""kind": "evaluation", "message": "division by zero""#));
+    }
+
+    #[test]
+    fn it_maps_errors_to_exit_statuses() {
+        let calculator = find("rational").ok().unwrap().create(&Settings::default());
+        assert_eq!(exit_status(&calculator.calculate("1 +").err().unwrap()), SYNTAX_ERROR);
+        assert_eq!(exit_status(&calculator.calculate("1 / 0").err().unwrap()), EVALUATION_ERROR);
+    }
+
+    #[test]
+    fn it_parses_subcommands() {
+        let opts = Opts::try_parse_from(&["expr_calc", "eval", "x = 2", "x / 3", "--mode", "rational"]).ok().unwrap();
+        match &opts.command {
+            Some(Command::Eval(it)) => assert_eq!(it.expressions, vec!["x = 2", "x / 3"]),
+            _ => panic!("expected eval"),
+        }
+        assert_eq!(opts.mode_name(), "rational");
+
+        let opts = Opts::try_parse_from(&["expr_calc", "--digits", "3", "2+2", "--", "-1"]).ok().unwrap();
+        assert!(opts.command.is_none());
+        assert_eq!(opts.expressions, vec!["2+2", "-1"]);
+        assert_eq!(opts.settings().format.digits, Some(3));
+
+        let opts = Opts::try_parse_from(&["expr_calc", "list", "--mode", "bool"]).ok().unwrap();
+        assert!(matches!(opts.command, Some(Command::List)));
+        assert_eq!(opts.mode.as_deref(), Some("bool"));
+        assert!(matches!(Opts::try_parse_from(&["expr_calc", "check", "--engine", "f64"]).ok().unwrap().command, Some(Command::Check(_))));
+    }
+
+    #[test]
+    fn it_completes_names() {
+        let mut calculator = find("float").ok().unwrap().create(&Settings::default());
+        calculator.execute("side = 2").ok().unwrap();
+        let names = calculator.names();
+        assert!(names.contains(&"sqrt".to_string()) && names.contains(&"pi".to_string()) && names.contains(&"side".to_string()));
+        assert!(!names.contains(&"+".to_string()));
+
+        assert_eq!(complete("1 + s", 5, &names), (4, vec!["side".to_string(), "sin".to_string(), "sqrt".to_string()]));
+        assert_eq!(complete("2pi + sq(1)", 8, &names), (6, vec!["sqrt".to_string()]));
+        assert_eq!(complete("3pi", 3, &names), (1, vec!["pi".to_string(), "piecewise".to_string()]));
+        assert_eq!(complete("1 + ", 4, &names), (4, names.clone()));
+    }
+
+    #[test]
+    fn it_keeps_results() {
+        let mut calculator = find("rational").ok().unwrap().create(&Settings::default());
+        calculator.execute("1/2").ok().unwrap();
+        calculator.keep_result(1);
+        calculator.execute("x = ans * 3").ok().unwrap();
+        calculator.keep_result(2);
+        assert_eq!(calculator.calculate("_1 + _2 + _").ok().unwrap(), "7/2");
+
+        calculator.execute("1 +").err().unwrap();
+        calculator.keep_result(3);
+        assert!(calculator.calculate("_3").is_err());
+
+        assert_eq!(calculator.results(), vec![(1, "1/2".to_string()), (2, "3/2".to_string())]);
+        calculator.set_number_format(NumberFormat { base: 16, ..NumberFormat::default() });
+        assert_eq!(calculator.calculate("ans * 2").ok().unwrap(), "0x3");
+        assert_eq!(calculator.calculate("_1").ok().unwrap(), "0x0.8");
+
+        let mut float = find("float").ok().unwrap().create(&Settings::default());
+        assert!(carry_over(calculator.as_ref(), float.as_mut()).is_empty());
+        assert_eq!(float.calculate("_1 + ans + x").ok().unwrap(), "3.5");
+        assert_eq!(float.results().iter().map(|(number, _)| *number).collect::<Vec<usize>>(), vec![1, 2]);
+        let mut boolean = find("bool").ok().unwrap().create(&Settings::default());
+        assert_eq!(carry_over(float.as_ref(), boolean.as_mut()), vec!["x", "_1", "_2"]);
+
+        calculator.clear_results();
+        assert!(calculator.calculate("ans").is_err());
+        assert!(calculator.calculate("_1").is_err());
+        assert!(calculator.results().is_empty());
+        assert_eq!(calculator.calculate("x").ok().unwrap(), "0x1.8");
+    }
+
+    #[test]
+    fn it_highlights_tokens() {
+        let calculator = find("float").ok().unwrap().create(&Settings::default());
+        let kinds = |statement: &str| -> Vec<(TokenKind, usize, usize)> {
+            calculator.spans(statement).iter().map(|it| (it.kind, it.start, it.end)).collect()
+        };
+        assert_eq!(kinds("x = cos(2) * pi"), vec![
+            (TokenKind::Variable, 0, 1), (TokenKind::Operator, 2, 3), (TokenKind::Function, 4, 7),
+            (TokenKind::Parenthesis, 7, 8), (TokenKind::Literal, 8, 9), (TokenKind::Parenthesis, 9, 10),
+            (TokenKind::Operator, 11, 12), (TokenKind::Constant, 13, 15),
+        ]);
+        assert_eq!(kinds("1 + + 2"), vec![(TokenKind::Literal, 0, 1), (TokenKind::Operator, 2, 3), (TokenKind::Invalid, 4, 5)]);
+        assert_eq!(kinds("1 + (2"), vec![(TokenKind::Literal, 0, 1), (TokenKind::Operator, 2, 3), (TokenKind::Invalid, 4, 5), (TokenKind::Literal, 5, 6)]);
+        assert_eq!(kinds("2 × 1.2.3 + 4"), vec![(TokenKind::Literal, 0, 1), (TokenKind::Operator, 2, 3), (TokenKind::Invalid, 4, 9)]);
+        assert_eq!(kinds("255 to hex"), vec![(TokenKind::Literal, 0, 3), (TokenKind::Operator, 4, 10)]);
+        assert_eq!(kinds("255 to hexx"), vec![(TokenKind::Literal, 0, 3), (TokenKind::Invalid, 4, 11)]);
+        assert_eq!(kinds("pi = 3"), vec![(TokenKind::Invalid, 0, 2), (TokenKind::Operator, 3, 4), (TokenKind::Literal, 5, 6)]);
+        assert_eq!(kinds("()"), vec![(TokenKind::Invalid, 0, 1), (TokenKind::Parenthesis, 1, 2)]);
+        assert!(calculator.calculate(" ").is_err());
+
+        let line = "(1+2)*a";
+        assert_eq!(colorize(line, 0, &calculator.spans(line)), concat!(
+            "\x1b[1m(\x1b[0m\x1b[36m1\x1b[0m\x1b[33m+\x1b[0m\x1b[36m2\x1b[0m\x1b[1m)\x1b[0m",
+            "\x1b[33m*\x1b[0m\x1b[32ma\x1b[0m"
+        ));
+        assert!(colorize("sin(1 +", 7, &calculator.spans("sin(1 +")).contains("\x1b[4;31m+\x1b[0m"));
+    }
+
+    #[test]
+    fn it_applies_shell_settings() {
+        let float = find("float").ok().unwrap();
+        let (mode, settings) = apply_setting("angle deg", float, Settings::default()).ok().unwrap();
+        assert_eq!(mode.create(&settings).calculate("sin(30) + cos(60)").ok().unwrap(), "1");
+        assert_eq!(f64_calculator_with(AngleUnit::Deg).calculate("sin(90)").ok().unwrap(), 1.0);
+
+        let (mode, settings) = apply_setting("precision 200", mode, settings).ok().unwrap();
+        assert_eq!(settings.precision, 200);
+        let (mode, settings) = apply_setting("mode rational", mode, settings).ok().unwrap();
+        assert_eq!(mode.name, "rational");
+        assert!(apply_setting("precision 1", mode, settings).is_err());
+        assert!(apply_setting("angle grad", mode, settings).is_err());
+        assert!(apply_setting("colour red", mode, settings).is_err());
+
+        assert_eq!(split_command(":set  precision 200 "), (":set", "precision 200"));
+        assert_eq!(split_command(":settings"), (":settings", ""));
+        assert_eq!(split_command(":loadx foo"), (":loadx", "foo"));
+        assert_eq!(split_command(":modefloat"), (":modefloat", ""));
+
+        let mut calculator = mode.create(&settings);
+        calculator.execute("x = 1/3").ok().unwrap();
+        calculator.keep_result(1);
+        calculator.execute("y = -x * 2").ok().unwrap();
+        assert_eq!(calculator.variables(), vec![("x".to_string(), "1/3".to_string()), ("y".to_string(), "-2/3".to_string())]);
+
+        let calculator = float.create(&Settings::default());
+        let help = operation_help(calculator.as_ref(), "×");
+        assert_eq!(help.len(), 1);
+        assert!(help[0].contains("x*y, x×y, x·y") && help[0].contains("Multiplication"));
+        assert_eq!(operation_help(calculator.as_ref(), "-").len(), 2);
+    }
+
+    #[test]
+    fn it_carries_variables_over_exactly() {
+        let statements = [
+            ("float", vec!["a = 1/3", "b = -2^70 / 7", "c = 1/0", "d = -1/0", "g = 0/0", "h = -0"]),
+            ("f64", vec!["a = 0.1 + 0.2", "b = 1/0", "c = 0/0"]),
+            ("mixed", vec!["a = 1/3", "b = 1 < 2", "c = -1/0"]),
+            ("rational", vec!["a = -1/3"]),
+            ("decimal", vec!["a = 0.10 + 0.20", "b = -1.5"]),
+            ("matrix", vec!["a = [[1, 2], [3, 4]] / 3", "b = [0.1, -2e-300]"]),
+        ];
+        for (name, statements) in statements.iter() {
+            let mode = find(name).ok().unwrap();
+            let mut calculator = mode.create(&Settings::default());
+            for statement in statements {
+                calculator.execute(statement).ok().unwrap();
+            }
+            let variables = calculator.variables();
+
+            let mut copy = mode.create(&Settings::default());
+            for (name, value) in variables.iter() {
+                copy.execute(&format!("{} = {}", name, value)).ok().unwrap();
+            }
+            assert_eq!(copy.variables(), variables);
+        }
+
+        let mut calculator = find("float").ok().unwrap().create(&Settings::default());
+        calculator.execute("x = 2/3").ok().unwrap();
+        calculator.set_number_format(NumberFormat { base: 16, ..NumberFormat::default() });
+        assert_eq!(calculator.calculate("x * 3").ok().unwrap(), "0x2");
+        assert_eq!(calculator.variables().len(), 1);
+    }
+
+    #[test]
+    fn it_saves_sessions() {
+        let mode = find("mixed").ok().unwrap();
+        let mut calculator = mode.create(&Settings::default());
+        for statement in ["x = 1/3", "y = -1/0", "z = 0/0", "b = 1 > 2"].iter() {
+            calculator.execute(statement).ok().unwrap();
+        }
+        let path = std::env::temp_dir().join(format!("expr_calc_session_{}.calc", std::process::id()));
+        let path = path.to_str().unwrap();
+        assert_eq!(save_session(path, mode, calculator.as_ref()), Ok(4));
+        let source = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(saved_mode(&source), Some("mixed"));
+        assert_eq!(saved_mode("x = 1"), None);
+
+        let mut loaded = mode.create(&Settings::default());
+        for statement in statements(&source) {
+            loaded.execute(&statement.text).ok().unwrap();
+        }
+        assert_eq!(loaded.variables(), calculator.variables());
+        assert_eq!(loaded.calculate("y").ok().unwrap(), "-inf");
+        assert_eq!(loaded.calculate("b").ok().unwrap(), "false");
+    }
+}
@@ -0,0 +1,86 @@
+use crate::ast::{ExprCalculator, Token, Variables};
+
+pub const MAX_VARIABLES: usize = 16;
+
+pub struct TruthTable {
+    pub expression: String,
+    pub variables: Vec<String>,
+    /// One row per assignment; row `i` assigns the bits of `i` to the variables, first variable is the most significant bit.
+    pub rows: Vec<(Vec<bool>, bool)>,
+}
+
+/// Evaluates the expression for every assignment of its variables, sorted by name.
+pub fn truth_table(calculator: &ExprCalculator<bool>, input: &str) -> Result<TruthTable, Token<bool>> {
+    let expression = calculator.compile(input)?;
+    let mut variables = expression.variables();
+    variables.sort();
+
+    if variables.len() > MAX_VARIABLES {
+        return Result::Err(Token::Failure {
+            pos: 0,
+            val: input.to_string(),
+            message: format!("too many variables: {}, at most {} supported", variables.len(), MAX_VARIABLES),
+        });
+    }
+
+    let mut rows = Vec::new();
+    for index in 0..1usize << variables.len() {
+        let assignment = assignment(index, variables.len());
+        let values: Variables<bool> = variables.iter().cloned().zip(assignment.iter().cloned()).collect();
+        let result = expression.calculate(&values)?;
+        rows.push((assignment, result));
+    }
+
+    return Result::Ok(TruthTable { expression: input.to_string(), variables, rows });
+}
+
+/// Values of `count` variables encoded by the bits of `index`, most significant first.
+pub fn assignment(index: usize, count: usize) -> Vec<bool> {
+    return (0..count).rev().map(|bit| index >> bit & 1 == 1).collect();
+}
+
+impl TruthTable {
+    /// Indexes of the rows where the expression is true.
+    pub fn minterms(&self) -> Vec<usize> {
+        return self.rows.iter().enumerate().filter(|(_, row)| row.1).map(|(index, _)| index).collect();
+    }
+
+    pub fn to_text(&self) -> String {
+        let widths: Vec<usize> = self.variables.iter().map(|it| it.chars().count()).collect();
+        let mut lines = Vec::new();
+
+        lines.push(format!("{} | {}", self.variables.join(" "), self.expression));
+        lines.push(format!("{}-+-{}", "-".repeat(widths.iter().sum::<usize>() + widths.len().saturating_sub(1)), "-".repeat(self.expression.chars().count())));
+
+        for (index, (assignment, result)) in self.rows.iter().enumerate() {
+            let values: Vec<String> = assignment.iter().zip(widths.iter())
+                .map(|(value, width)| format!("{:<1$}", bit(*value), width))
+                .collect();
+            let marker = if *result { format!("  <- m{}", index) } else { String::new() };
+            lines.push(format!("{} | {}{}", values.join(" "), bit(*result), marker));
+        }
+
+        let minterms: Vec<String> = self.minterms().iter().map(|it| it.to_string()).collect();
+        lines.push(format!("Minterms: m({})", minterms.join(", ")));
+
+        return lines.join("\n");
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut header = self.variables.clone();
+        header.push(format!("\"{}\"", self.expression.replace('"', "\"\"")));
+
+        let mut lines = vec![header.join(",")];
+        for (assignment, result) in self.rows.iter() {
+            let mut values: Vec<&str> = assignment.iter().map(|it| bit(*it)).collect();
+            values.push(bit(*result));
+            lines.push(values.join(","));
+        }
+
+        return lines.join("\n");
+    }
+}
+
+fn bit(value: bool) -> &'static str {
+    return if value { "1" } else { "0" };
+}
@@ -0,0 +1,73 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use rug::Float;
+
+use crate::format::{Format, NumberFormat};
+
+/// Value of the mixed calculator: comparisons turn numbers into booleans within one expression.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Number(Float),
+    Boolean(bool),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        return match self {
+            Value::Number(_) => "number",
+            Value::Boolean(_) => "boolean",
+        };
+    }
+
+    pub fn number(&self) -> Result<&Float, String> {
+        return match self {
+            Value::Number(value) => Result::Ok(value),
+            _ => Result::Err(format!("type error: expected number, got {}", self.type_name())),
+        };
+    }
+
+    pub fn boolean(&self) -> Result<bool, String> {
+        return match self {
+            Value::Boolean(value) => Result::Ok(*value),
+            _ => Result::Err(format!("type error: expected boolean, got {}", self.type_name())),
+        };
+    }
+
+    /// Equality of two values of the same type.
+    pub fn equals(&self, other: &Value) -> Result<bool, String> {
+        if self.type_name() != other.type_name() {
+            return Result::Err(format!("type error: can't compare {} with {}", self.type_name(), other.type_name()));
+        }
+
+        return Result::Ok(self == other);
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        return match self {
+            Value::Number(value) => write!(f, "{}", value),
+            Value::Boolean(value) => write!(f, "{}", value),
+        };
+    }
+}
+
+impl Format for Value {
+    fn format(&self, format: &NumberFormat) -> String {
+        return match self {
+            Value::Number(value) => value.format(format),
+            Value::Boolean(value) => value.format(format),
+        };
+    }
+
+    fn type_name(&self) -> &'static str {
+        return Value::type_name(self);
+    }
+
+    fn to_input(&self) -> String {
+        return match self {
+            Value::Number(value) => value.to_input(),
+            Value::Boolean(value) => value.to_input(),
+        };
+    }
+}
@@ -0,0 +1,69 @@
+use std::process::{Command, Stdio};
+
+/// Runs the binary with `args`, gives the exit status, stdout and stderr.
+fn run(args: &[&str]) -> (i32, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_expr_calc"))
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+
+    return (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    );
+}
+
+#[test]
+fn it_exits_with_the_status_of_the_error() {
+    let (status, stdout, stderr) = run(&["eval", "1 +"]);
+    assert_eq!(status, 2);
+    assert_eq!(stdout, "");
+    assert!(stderr.contains("syntax error"));
+
+    let (status, stdout, stderr) = run(&["eval", "--mode", "rational", "1/0"]);
+    assert_eq!(status, 3);
+    assert_eq!(stdout, "");
+    assert!(stderr.contains("division by zero"));
+
+    let (status, stdout, stderr) = run(&["eval", "--mode", "nope", "1"]);
+    assert_eq!(status, 1);
+    assert_eq!(stdout, "");
+    assert!(stderr.contains("unknown mode 'nope'"));
+
+    let (status, stdout, stderr) = run(&["eval", "--mode", "rational", "1/2 + 1/3", "1/0", "1 +"]);
+    assert_eq!(status, 3);
+    assert_eq!(stdout, "5/6\n");
+    assert_eq!(stderr.matches("error").count(), 2);
+
+    assert_eq!(run(&["eval", "2 + 2"]), (0, "4\n".to_string(), String::new()));
+}
+
+#[test]
+fn it_prints_the_analysis_options_as_json() {
+    let (status, stdout, _) = run(&["--output", "json", "--truth-table", "a & b"]);
+    assert_eq!(status, 0);
+    assert!(stdout.starts_with("{\"input\": \"a & b\", \"value\": \""));
+    assert!(stdout.ends_with("\"type\": \"table\"}\n"));
+
+    let (status, stdout, _) = run(&["--output", "json", "--tautology", "a | !a"]);
+    assert_eq!(status, 0);
+    assert!(stdout.ends_with("\"type\": \"verdict\"}\n"));
+
+    let (status, stdout, stderr) = run(&["--output", "json", "--truth-table", "a &"]);
+    assert_eq!(status, 2);
+    assert!(stdout.contains("\"kind\": \"syntax\""));
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn it_only_checks_the_analysis_options() {
+    assert_eq!(run(&["--check", "--truth-table", "a & b"]), (0, String::new(), String::new()));
+    assert_eq!(run(&["--check", "--compare", "2 + 2"]), (0, String::new(), String::new()));
+
+    let (status, stdout, stderr) = run(&["--check", "--minimize", "a &"]);
+    assert_eq!(status, 2);
+    assert_eq!(stdout, "");
+    assert!(stderr.contains("syntax error"));
+}